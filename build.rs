@@ -0,0 +1,126 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+// Records the git revision the binary was built from so that exported results
+// can be traced back to the exact candidate code that produced them, and how
+// it was compiled (rustc, profile, target-cpu, target features, LTO, codegen
+// units, panic strategy), since a candidate's speedup can depend on that as
+// much as on its code.
+
+/// Profile settings that change the generated code; each is read from its
+/// CARGO_PROFILE_<NAME>_<SETTING> variable if set, else from Cargo.toml.
+const PROFILE_SETTINGS: [&str; 3] = ["lto", "codegen-units", "panic"];
+
+fn main() {
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/index");
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=Cargo.toml");
+
+    let revision =
+        git(&["rev-parse", "--short=12", "HEAD"]).unwrap_or_else(|| "unknown".to_string());
+    let dirty =
+        git(&["status", "--porcelain", "--untracked-files=no"]).is_some_and(|s| !s.is_empty());
+
+    println!(
+        "cargo:rustc-env=GIT_REVISION={}{}",
+        revision,
+        if dirty { "-dirty" } else { "" }
+    );
+
+    let profile = profile();
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc_version = Command::new(rustc)
+        .arg("--version")
+        .output()
+        .ok()
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    let rustflags = env::var("CARGO_ENCODED_RUSTFLAGS").unwrap_or_default();
+    let target_cpu = target_cpu(&rustflags).unwrap_or_else(|| "generic".to_string());
+
+    println!("cargo:rustc-env=BUILD_RUSTC={}", rustc_version);
+    println!("cargo:rustc-env=BUILD_PROFILE={}", profile);
+    println!(
+        "cargo:rustc-env=BUILD_OPT_LEVEL={}",
+        env::var("OPT_LEVEL").unwrap_or_default()
+    );
+    println!("cargo:rustc-env=BUILD_TARGET_CPU={}", target_cpu);
+    println!(
+        "cargo:rustc-env=BUILD_TARGET_FEATURES={}",
+        env::var("CARGO_CFG_TARGET_FEATURE").unwrap_or_default()
+    );
+    for setting in PROFILE_SETTINGS {
+        let variable = format!(
+            "CARGO_PROFILE_{}_{}",
+            profile.to_uppercase().replace('-', "_"),
+            setting.to_uppercase().replace('-', "_")
+        );
+        println!("cargo:rerun-if-env-changed={}", variable);
+        let value = env::var(&variable)
+            .ok()
+            .or_else(|| manifest_setting(&profile, setting))
+            .unwrap_or_else(|| "default".to_string());
+        println!(
+            "cargo:rustc-env=BUILD_{}={}",
+            setting.to_uppercase().replace('-', "_"),
+            value
+        );
+    }
+}
+
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Name of the profile being built. Cargo only tells build scripts "debug" or
+/// "release", so it is taken from the output directory instead,
+/// target/<profile>/build/<package>-<hash>/out, where dev builds go to "debug".
+fn profile() -> String {
+    let out_dir = env::var("OUT_DIR").unwrap_or_default();
+    match Path::new(&out_dir)
+        .ancestors()
+        .nth(3)
+        .and_then(|dir| dir.file_name())
+        .and_then(|name| name.to_str())
+    {
+        Some("debug") => "dev".to_string(),
+        Some(name) => name.to_string(),
+        None => env::var("PROFILE").unwrap_or_else(|_| "unknown".to_string()),
+    }
+}
+
+/// The value of -C target-cpu in the rustflags, which cargo separates with 0x1f.
+fn target_cpu(rustflags: &str) -> Option<String> {
+    let flags: Vec<&str> = rustflags.split('\x1f').collect();
+    flags.iter().enumerate().find_map(|(i, flag)| {
+        let codegen = match flag.strip_prefix("-C") {
+            Some("") => flags.get(i + 1).copied()?,
+            Some(codegen) => codegen,
+            None => return None,
+        };
+        codegen.strip_prefix("target-cpu=").map(str::to_string)
+    })
+}
+
+/// `setting` in the [profile.<profile>] section of Cargo.toml, unquoted.
+fn manifest_setting(profile: &str, setting: &str) -> Option<String> {
+    let manifest = fs::read_to_string("Cargo.toml").ok()?;
+    let section = format!("[profile.{}]", profile);
+    manifest
+        .lines()
+        .map(str::trim)
+        .skip_while(|line| *line != section)
+        .skip(1)
+        .take_while(|line| !line.starts_with('['))
+        .find_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            (key.trim() == setting).then(|| value.trim().trim_matches('"').to_string())
+        })
+}
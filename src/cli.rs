@@ -0,0 +1,442 @@
+use chrono::NaiveDate;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+
+use crate::clock;
+use crate::common::AICodeGenStatus;
+use crate::fuzz;
+use crate::isolate;
+
+pub const DEFAULT_HISTORY: &str = "results/history.jsonl";
+pub const DEFAULT_PROFILES: &str = "profiles.json";
+
+#[derive(Parser)]
+#[command(
+    version,
+    about = "Measures how much faster AI generated Rust functions are than a hand-written baseline"
+)]
+pub struct Cli {
+    /// Defaults to `run` with no filters.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Verify and time the candidates against the baseline
+    Run(RunArgs),
+    /// List the tasks and the candidates every engine has for them
+    List(Filter),
+    /// Rewrite the results tables in the README from a new run or saved results
+    UpdateReadme(UpdateReadmeArgs),
+    /// Diff two runs from the history, highlighting significant changes
+    Compare(CompareArgs),
+    /// Add the code in a saved AI response as a new candidate module
+    Ingest(IngestArgs),
+    /// Ask a model for a candidate, retrying once with the compiler errors, and add it
+    Generate(GenerateArgs),
+    /// Compare the candidates with the baseline on random inputs, shrinking any counterexample
+    Fuzz(FuzzArgs),
+    /// Time the candidates at growing input sizes and fit their complexity exponent
+    Sweep(SweepArgs),
+    /// Build and run the harness under several compiler profiles and compare the speedups
+    Matrix(MatrixArgs),
+    #[command(name = isolate::CHILD_COMMAND, hide = true)]
+    RunCandidate {
+        family: String,
+        module: usize,
+        function: usize,
+        #[command(flatten)]
+        timing: Timing,
+    },
+    #[command(name = fuzz::CHILD_COMMAND, hide = true)]
+    FuzzCandidate {
+        family: String,
+        module: usize,
+        function: usize,
+        seed: u64,
+        cases: u64,
+    },
+}
+
+/// Restricts a command to some of the engines and tasks. Empty means all.
+#[derive(Args, Default)]
+pub struct Filter {
+    /// Only engines whose name contains this text, ignoring case (repeatable)
+    #[arg(long = "engine", value_name = "ENGINE")]
+    pub engines: Vec<String>,
+
+    /// Only the task with exactly this name, ignoring case (repeatable)
+    #[arg(long = "task", value_name = "TASK")]
+    pub tasks: Vec<String>,
+}
+
+impl Filter {
+    pub fn engine_selected(&self, engine_name: &str) -> bool {
+        let engine_name = engine_name.to_lowercase();
+        self.engines.is_empty()
+            || self
+                .engines
+                .iter()
+                .any(|engine| engine_name.contains(&engine.to_lowercase()))
+    }
+
+    pub fn task_selected(&self, task_name: &str) -> bool {
+        self.tasks.is_empty()
+            || self
+                .tasks
+                .iter()
+                .any(|task| task.eq_ignore_ascii_case(task_name))
+    }
+}
+
+#[derive(Args)]
+pub struct RunArgs {
+    #[command(flatten)]
+    pub filter: Filter,
+
+    #[command(flatten)]
+    pub timing: Timing,
+
+    /// How the results are printed
+    #[arg(long, value_enum, default_value_t = Format::Table)]
+    pub format: Format,
+
+    /// Also write the results, with host and git revision, as a JSON document
+    #[arg(long, value_name = "FILE")]
+    pub json: Option<PathBuf>,
+
+    /// Also write the results, with host and git revision, as a CSV file
+    #[arg(long, value_name = "FILE")]
+    pub csv: Option<PathBuf>,
+
+    /// History file the results are appended to
+    #[arg(long, value_name = "FILE", default_value = DEFAULT_HISTORY)]
+    pub history: PathBuf,
+
+    /// Do not append the results to the history file
+    #[arg(long)]
+    pub no_history: bool,
+
+    /// Lower the nice value of the harness and the candidates (needs root or CAP_SYS_NICE)
+    #[arg(long)]
+    pub high_priority: bool,
+}
+
+impl Default for RunArgs {
+    fn default() -> Self {
+        RunArgs {
+            filter: Filter::default(),
+            timing: Timing::default(),
+            format: Format::Table,
+            json: None,
+            csv: None,
+            history: PathBuf::from(DEFAULT_HISTORY),
+            no_history: false,
+            high_priority: false,
+        }
+    }
+}
+
+/// How each candidate is timed, passed on to the child process that times it.
+#[derive(Args, Clone)]
+pub struct Timing {
+    /// Seconds each candidate is timed for
+    #[arg(long, default_value_t = 2)]
+    pub duration: u64,
+
+    /// Read the clock once per batch of calls, with the loop overhead
+    /// subtracted, and from the TSC on x86_64; for functions that take nanoseconds
+    #[arg(long)]
+    pub batched: bool,
+
+    /// Time on an input of this size (string length, value of n) instead of
+    /// each task's benchmark input
+    #[arg(long)]
+    pub input_size: Option<u64>,
+
+    /// Time on this many inputs of about the same cost, in rotation, starting
+    /// with the usual one, so that no answer can be precomputed
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    pub input_pool: usize,
+
+    /// Run every candidate on this CPU only (Linux)
+    #[arg(long, value_name = "CPU")]
+    pub pin_cpu: Option<usize>,
+
+    /// Also read cycles, instructions, branch and cache misses per call from
+    /// the hardware performance counters (Linux)
+    #[arg(long)]
+    pub counters: bool,
+
+    /// Also count allocations and bytes allocated per call and the peak heap
+    #[arg(long)]
+    pub allocations: bool,
+
+    /// Also time every call of a short run for the latency percentiles
+    #[arg(long)]
+    pub latency: bool,
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Timing {
+            duration: 2,
+            batched: false,
+            input_size: None,
+            input_pool: 1,
+            pin_cpu: None,
+            counters: false,
+            allocations: false,
+            latency: false,
+        }
+    }
+}
+
+impl Timing {
+    /// Clock the candidates are timed with, for the exports.
+    pub fn timer(&self) -> String {
+        if self.batched {
+            format!("batched-{}", clock::name())
+        } else {
+            "instant".to_string()
+        }
+    }
+
+    /// The options as command-line arguments, for the child process.
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = vec![
+            format!("--duration={}", self.duration),
+            format!("--input-pool={}", self.input_pool),
+        ];
+        if self.batched {
+            args.push("--batched".to_string());
+        }
+        if let Some(size) = self.input_size {
+            args.push(format!("--input-size={}", size));
+        }
+        if let Some(cpu) = self.pin_cpu {
+            args.push(format!("--pin-cpu={}", cpu));
+        }
+        if self.counters {
+            args.push("--counters".to_string());
+        }
+        if self.allocations {
+            args.push("--allocations".to_string());
+        }
+        if self.latency {
+            args.push("--latency".to_string());
+        }
+        args
+    }
+}
+
+#[derive(Args)]
+pub struct UpdateReadmeArgs {
+    /// Results saved with `run --json` to use instead of running the suite
+    #[arg(long, value_name = "FILE")]
+    pub from: Option<PathBuf>,
+
+    /// README to rewrite between its results markers
+    #[arg(long, value_name = "FILE", default_value = "README.md")]
+    pub readme: PathBuf,
+
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+#[derive(Args)]
+pub struct CompareArgs {
+    /// Older run: its number as shown by --runs, or its timestamp (or a prefix
+    /// of it). Defaults to the second most recent run.
+    pub old: Option<String>,
+
+    /// Newer run, same forms as OLD. Defaults to the most recent run.
+    pub new: Option<String>,
+
+    /// List the runs in the history instead of comparing
+    #[arg(long)]
+    pub runs: bool,
+
+    /// History file to read
+    #[arg(long, value_name = "FILE", default_value = DEFAULT_HISTORY)]
+    pub history: PathBuf,
+
+    #[command(flatten)]
+    pub filter: Filter,
+}
+
+#[derive(Args)]
+pub struct IngestArgs {
+    /// The AI's answer, saved as Markdown or text
+    pub response: PathBuf,
+
+    /// Engine that answered, as shown in the results, e.g. "Mistral Large 2"
+    #[arg(long)]
+    pub engine: String,
+
+    /// Task the prompt was for
+    #[arg(long)]
+    pub task: String,
+
+    /// Day the answer was generated, YYYY-MM-DD [default: today]
+    #[arg(long)]
+    pub date: Option<NaiveDate>,
+
+    /// Prompt the answer was given for [default: the one in the README]
+    #[arg(long)]
+    pub prompt: Option<String>,
+
+    /// Short name for the prompt, recorded with the candidate when it is not
+    /// the one in the README
+    #[arg(long, value_name = "ID")]
+    pub prompt_id: Option<String>,
+
+    /// Declared status of the answer
+    #[arg(long, default_value_t = AICodeGenStatus::Ok)]
+    pub status: AICodeGenStatus,
+
+    /// Attempt the answer came from [default: 2 for the SecondTry statuses, else 1]
+    #[arg(long)]
+    pub attempt: Option<u32>,
+
+    /// Version of the engine, when it shows one
+    #[arg(long, value_name = "VERSION")]
+    pub model_version: Option<String>,
+
+    /// Anything else worth knowing about the answer
+    #[arg(long)]
+    pub notes: Option<String>,
+
+    /// Module to create for an engine without candidates yet, as in
+    /// src/module_<MODULE> [default: derived from the engine name]
+    #[arg(long)]
+    pub module: Option<String>,
+
+    /// Source directory of the harness
+    #[arg(long, value_name = "DIR", default_value = "src")]
+    pub src: PathBuf,
+}
+
+#[derive(Args)]
+pub struct GenerateArgs {
+    /// Task to ask for
+    #[arg(long)]
+    pub task: String,
+
+    /// Model to ask, as the API names it
+    #[arg(long)]
+    pub model: String,
+
+    /// Engine name recorded with the candidate [default: the model]
+    #[arg(long)]
+    pub engine: Option<String>,
+
+    /// Base URL of an OpenAI-compatible chat completions API
+    #[arg(long, value_name = "URL", default_value = "https://api.openai.com/v1")]
+    pub base_url: String,
+
+    /// Environment variable holding the API key, if the API needs one
+    #[arg(long, value_name = "VAR", default_value = "OPENAI_API_KEY")]
+    pub api_key_env: String,
+
+    /// Replay this saved answer instead of calling the API; repeat for the
+    /// answer to the compiler errors
+    #[arg(long = "fixture", value_name = "FILE")]
+    pub fixtures: Vec<PathBuf>,
+
+    /// Crate the answers are compiled and checked in
+    #[arg(long, value_name = "DIR", default_value = "target/scratch")]
+    pub scratch: PathBuf,
+
+    /// Directory the conversations are saved in, one subdirectory per engine
+    #[arg(long, value_name = "DIR", default_value = "responses")]
+    pub responses: PathBuf,
+
+    /// Module to create for an engine without candidates yet, as in
+    /// src/module_<MODULE> [default: derived from the engine name]
+    #[arg(long)]
+    pub module: Option<String>,
+
+    /// Source directory of the harness
+    #[arg(long, value_name = "DIR", default_value = "src")]
+    pub src: PathBuf,
+}
+
+#[derive(Copy, Clone, PartialEq, ValueEnum)]
+pub enum Format {
+    /// Boxed text tables
+    Table,
+    /// Markdown tables, as used in the README
+    Markdown,
+}
+
+#[derive(Args)]
+pub struct FuzzArgs {
+    #[command(flatten)]
+    pub filter: Filter,
+
+    /// Random inputs each candidate is tried on
+    #[arg(long, default_value_t = 1000)]
+    pub cases: u64,
+
+    /// Seed of the random inputs, to reproduce an earlier run [default: random]
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Seconds a candidate may take for all its cases before it is stopped
+    #[arg(long, value_name = "SECONDS", default_value_t = 60)]
+    pub timeout: u64,
+}
+
+#[derive(Args)]
+pub struct SweepArgs {
+    #[command(flatten)]
+    pub filter: Filter,
+
+    /// Seconds each candidate is timed for at each size
+    #[arg(long, value_name = "SECONDS", default_value_t = 1)]
+    pub duration: u64,
+
+    /// Sizes timed, spaced geometrically from the smallest to the largest
+    #[arg(long, default_value_t = 5)]
+    pub steps: u32,
+
+    /// Smallest input size [default: the task's]
+    #[arg(long, value_name = "SIZE")]
+    pub from: Option<u64>,
+
+    /// Largest input size [default: the task's]
+    #[arg(long, value_name = "SIZE")]
+    pub to: Option<u64>,
+
+    /// How the tables are printed
+    #[arg(long, value_enum, default_value_t = Format::Table)]
+    pub format: Format,
+}
+
+#[derive(Args)]
+pub struct MatrixArgs {
+    #[command(flatten)]
+    pub filter: Filter,
+
+    /// JSON file defining the profiles
+    #[arg(long, value_name = "FILE", default_value = DEFAULT_PROFILES)]
+    pub config: PathBuf,
+
+    /// Only the profile with this name (repeatable)
+    #[arg(long = "profile", value_name = "PROFILE")]
+    pub profiles: Vec<String>,
+
+    /// Seconds each candidate is timed for under each profile
+    #[arg(long, value_name = "SECONDS", default_value_t = 1)]
+    pub duration: u64,
+
+    /// How the tables are printed
+    #[arg(long, value_enum, default_value_t = Format::Table)]
+    pub format: Format,
+
+    /// Also write every profile's results to this JSON file
+    #[arg(long, value_name = "FILE")]
+    pub json: Option<PathBuf>,
+}
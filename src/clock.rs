@@ -0,0 +1,157 @@
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+// A call that takes a few nanoseconds cannot be timed by reading the clock
+// around it, or even once per loop iteration as `stats::measure` does: the
+// clock read costs more than the call. Batched timing reads the clock once per
+// batch of calls, sized so that a batch takes well above the clock's cost, and
+// subtracts what an empty batch costs. On x86_64 with an invariant TSC the
+// clock is the time-stamp counter, read with rdtsc after an lfence (so that
+// earlier instructions are done) at the start and rdtscp followed by an
+// lfence (so that later ones have not started) at the end, and converted to
+// seconds with a frequency calibrated against Instant; elsewhere it is Instant.
+
+/// How long the TSC frequency is calibrated for.
+const CALIBRATION: Duration = Duration::from_millis(20);
+
+/// How long a batch should take at least.
+const TARGET_BATCH: Duration = Duration::from_micros(20);
+
+const MAX_BATCH: u64 = 1 << 24;
+
+/// Empty batches timed to find the loop overhead; the fastest is used.
+const OVERHEAD_RUNS: usize = 101;
+
+pub enum Clock {
+    Tsc { ticks_per_sec: f64 },
+    Instant { origin: Instant },
+}
+
+impl Clock {
+    /// The TSC when it can be used, calibrated, and Instant otherwise.
+    pub fn detect() -> Clock {
+        if !tsc_available() {
+            return Clock::Instant {
+                origin: Instant::now(),
+            };
+        }
+        let (start, ticks_start) = (Instant::now(), tsc::start());
+        while start.elapsed() < CALIBRATION {}
+        let ticks = tsc::stop().wrapping_sub(ticks_start);
+        Clock::Tsc {
+            ticks_per_sec: ticks as f64 / start.elapsed().as_secs_f64(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn start(&self) -> u64 {
+        match self {
+            Clock::Tsc { .. } => tsc::start(),
+            Clock::Instant { origin } => origin.elapsed().as_nanos() as u64,
+        }
+    }
+
+    #[inline(always)]
+    pub fn stop(&self) -> u64 {
+        match self {
+            Clock::Tsc { .. } => tsc::stop(),
+            Clock::Instant { origin } => origin.elapsed().as_nanos() as u64,
+        }
+    }
+
+    pub fn seconds(&self, ticks: u64) -> f64 {
+        match self {
+            Clock::Tsc { ticks_per_sec } => ticks as f64 / ticks_per_sec,
+            Clock::Instant { .. } => ticks as f64 / 1e9,
+        }
+    }
+
+    /// Ticks taken by `batch` calls of `f`.
+    #[inline(always)]
+    pub fn time_batch<F: FnMut()>(&self, f: &mut F, batch: u64) -> u64 {
+        let start = self.start();
+        for _ in 0..batch {
+            f();
+        }
+        self.stop().wrapping_sub(start)
+    }
+
+    /// Calls per batch for `f`: the smallest power of two whose batch takes
+    /// at least TARGET_BATCH.
+    pub fn batch_size<F: FnMut()>(&self, f: &mut F) -> u64 {
+        let mut batch = 1;
+        while batch < MAX_BATCH
+            && self.seconds(self.time_batch(f, batch)) < TARGET_BATCH.as_secs_f64()
+        {
+            batch *= 2;
+        }
+        batch
+    }
+
+    /// Ticks taken by a batch of `batch` calls that do nothing: the clock
+    /// reads and the loop, to be subtracted from every batch.
+    pub fn overhead(&self, batch: u64) -> u64 {
+        (0..OVERHEAD_RUNS)
+            .map(|_| self.time_batch(&mut || black_box(()), batch))
+            .min()
+            .unwrap_or(0)
+    }
+}
+
+/// Whether `Clock::detect` would use the time-stamp counter.
+pub fn tsc_available() -> bool {
+    tsc::available()
+}
+
+/// Name of the clock batched timing uses on this machine, for the exports.
+pub fn name() -> &'static str {
+    if tsc_available() { "tsc" } else { "instant" }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod tsc {
+    use std::arch::x86_64::{__cpuid, __rdtscp, _mm_lfence, _rdtsc};
+
+    /// An invariant TSC (same rate in every P-state and C-state) and rdtscp.
+    pub fn available() -> bool {
+        let max_extended = __cpuid(0x8000_0000).eax;
+        max_extended >= 0x8000_0007
+            && __cpuid(0x8000_0001).edx & (1 << 27) != 0
+            && __cpuid(0x8000_0007).edx & (1 << 8) != 0
+    }
+
+    #[inline(always)]
+    pub fn start() -> u64 {
+        unsafe {
+            _mm_lfence();
+            let ticks = _rdtsc();
+            _mm_lfence();
+            ticks
+        }
+    }
+
+    #[inline(always)]
+    pub fn stop() -> u64 {
+        unsafe {
+            let mut aux = 0;
+            let ticks = __rdtscp(&mut aux);
+            _mm_lfence();
+            ticks
+        }
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+mod tsc {
+    pub fn available() -> bool {
+        false
+    }
+
+    pub fn start() -> u64 {
+        unreachable!("no TSC on this architecture")
+    }
+
+    pub fn stop() -> u64 {
+        unreachable!("no TSC on this architecture")
+    }
+}
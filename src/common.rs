@@ -1,13 +1,19 @@
 use chrono::NaiveDate;
 use core::fmt;
 use prettytable::{Cell, Row, Table, format};
-use std::cell::UnsafeCell;
 use std::cmp::Ordering;
-use std::collections::HashMap;
-use std::time::{Duration, Instant};
-
-type FnAITest = fn(&str, &str) -> usize;
-type FnAITest2 = fn(u64) -> u64;
+use std::hint::black_box;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::cli::{Format, Timing};
+use crate::clock::Clock;
+use crate::heap::Allocations;
+use crate::latency::{self, Latency};
+use crate::perf::{self, Counters};
+use crate::stats;
+use crate::stats::{Speedup, Summary};
+use crate::task::Task;
 
 #[derive(Copy, Clone, PartialEq, Debug)]
 #[allow(dead_code)]
@@ -18,6 +24,34 @@ pub enum AICodeGenStatus {
     SecondTryCompileError,
     IncorrectResult,
     AIRefusedToAnswer,
+    Timeout,
+    Panicked,
+    Crashed,
+    /// Correct, but answers repeated calls from results kept between calls.
+    CachesResults,
+    /// The harness could not run the candidate's child process or read its report.
+    Failed,
+}
+
+impl AICodeGenStatus {
+    /// False for statuses whose function is only a stub because the AI never
+    /// produced code that compiled.
+    pub fn has_code(&self) -> bool {
+        !matches!(
+            self,
+            AICodeGenStatus::CompileError
+                | AICodeGenStatus::SecondTryCompileError
+                | AICodeGenStatus::AIRefusedToAnswer
+        )
+    }
+
+    /// The attempt a declared status is assumed to come from.
+    pub fn attempt(&self) -> u32 {
+        match self {
+            AICodeGenStatus::SecondTryOk | AICodeGenStatus::SecondTryCompileError => 2,
+            _ => 1,
+        }
+    }
 }
 
 impl fmt::Display for AICodeGenStatus {
@@ -29,206 +63,230 @@ impl fmt::Display for AICodeGenStatus {
             AICodeGenStatus::SecondTryCompileError => "SecondTryCompileError",
             AICodeGenStatus::IncorrectResult => "IncorrectResult",
             AICodeGenStatus::AIRefusedToAnswer => "AIRefusedToAnswer",
+            AICodeGenStatus::Timeout => "Timeout",
+            AICodeGenStatus::Panicked => "Panicked",
+            AICodeGenStatus::Crashed => "Crashed",
+            AICodeGenStatus::CachesResults => "CachesResults",
+            AICodeGenStatus::Failed => "Failed",
         };
         write!(f, "{}", s)
     }
 }
 
-#[derive(Debug)]
-pub struct CandidateInfo {
+impl FromStr for AICodeGenStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "Ok" => AICodeGenStatus::Ok,
+            "CompileError" => AICodeGenStatus::CompileError,
+            "SecondTryOk" => AICodeGenStatus::SecondTryOk,
+            "SecondTryCompileError" => AICodeGenStatus::SecondTryCompileError,
+            "IncorrectResult" => AICodeGenStatus::IncorrectResult,
+            "AIRefusedToAnswer" => AICodeGenStatus::AIRefusedToAnswer,
+            "Timeout" => AICodeGenStatus::Timeout,
+            "Panicked" => AICodeGenStatus::Panicked,
+            "Crashed" => AICodeGenStatus::Crashed,
+            "CachesResults" => AICodeGenStatus::CachesResults,
+            "Failed" => AICodeGenStatus::Failed,
+            _ => return Err(format!("unknown status '{}'", s)),
+        })
+    }
+}
+
+/// The candidates of one engine for one family of tasks.
+pub struct CandidateInfo<T: Task> {
     pub engine_name: String,
-    pub function_names: Vec<String>,
-    pub dates: Vec<NaiveDate>,
-    pub status: Vec<AICodeGenStatus>,
-    pub functions: Vec<FnAITest>,
+    pub candidates: Vec<Candidate<T>>,
 }
 
-impl CandidateInfo {
-    pub fn new(
-        engine_name: String,
-        function_names: Vec<String>,
-        dates: Vec<NaiveDate>,
-        status: Vec<AICodeGenStatus>,
-        functions: Vec<FnAITest>,
-    ) -> Self {
+impl<T: Task> CandidateInfo<T> {
+    pub fn new(engine_name: &str, candidates: Vec<Candidate<T>>) -> Self {
         CandidateInfo {
-            engine_name,
-            function_names,
-            dates,
-            status,
-            functions,
+            engine_name: engine_name.to_string(),
+            candidates,
         }
     }
+
+    /// Index of the first candidate for the task.
+    pub fn position(&self, task_name: &str) -> Option<usize> {
+        self.candidates
+            .iter()
+            .position(|candidate| candidate.task == task_name)
+    }
 }
 
-#[derive(Debug)]
-pub struct CandidateInfo2 {
-    pub engine_name: String,
-    pub function_names: Vec<String>,
-    pub dates: Vec<NaiveDate>,
-    pub status: Vec<AICodeGenStatus>,
-    pub functions: Vec<FnAITest2>,
+/// One attempt of an engine at a task. Made with `candidate!`, which records
+/// the function's name so that it can be checked against the task at startup.
+pub struct Candidate<T: Task> {
+    /// Name of the task, as in task.rs.
+    pub task: &'static str,
+    pub function: T::Func,
+    /// The function as written in `candidate!`, e.g. levenshtein_distance2.
+    pub function_name: &'static str,
+    pub date: NaiveDate,
+    pub status: AICodeGenStatus,
+    /// Version or model id of the engine, when it shows one.
+    pub model_version: Option<&'static str>,
+    /// Which prompt was used, None for the one in the README.
+    pub prompt_id: Option<&'static str>,
+    /// 1 for the first answer, 2 for the answer to the compiler errors.
+    pub attempt: u32,
+    pub notes: Option<&'static str>,
 }
 
-impl CandidateInfo2 {
+impl<T: Task> Candidate<T> {
     pub fn new(
-        engine_name: String,
-        function_names: Vec<String>,
-        dates: Vec<NaiveDate>,
-        status: Vec<AICodeGenStatus>,
-        functions: Vec<FnAITest2>,
+        task: &'static str,
+        function: T::Func,
+        function_name: &'static str,
+        date: NaiveDate,
+        status: AICodeGenStatus,
     ) -> Self {
-        CandidateInfo2 {
-            engine_name,
-            function_names,
-            dates,
+        Candidate {
+            task,
+            function,
+            function_name,
+            date,
             status,
-            functions,
+            model_version: None,
+            prompt_id: None,
+            attempt: status.attempt(),
+            notes: None,
         }
     }
-}
 
-pub fn run_for_duration<F>(f: F, input1: &str, input2: &str, duration_sec: u64) -> (usize, f64)
-where
-    F: Fn(&str, &str) -> usize,
-{
-    let duration = Duration::new(duration_sec, 0);
-    let start_time = Instant::now();
-    let mut run_count = 0;
-    let mut result = 0;
-
-    while Instant::now().duration_since(start_time) < duration {
-        result = f(input1, input2);
-        run_count += 1;
-        let result_cell = UnsafeCell::new(result);
-        unsafe {
-            std::ptr::write_volatile(result_cell.get(), result);
-        }
+    pub fn model_version(mut self, model_version: &'static str) -> Self {
+        self.model_version = Some(model_version);
+        self
     }
 
-    let total_runtime = Instant::now().duration_since(start_time).as_secs_f64();
-    (result, run_count as f64 / total_runtime)
-}
+    #[allow(dead_code)] // for candidates ingested with --prompt-id
+    pub fn prompt_id(mut self, prompt_id: &'static str) -> Self {
+        self.prompt_id = Some(prompt_id);
+        self
+    }
 
-pub fn run_for_duration2<F>(f: F, input: u64, duration_sec: u64) -> (u64, f64)
-where
-    F: Fn(u64) -> u64,
-{
-    let duration = Duration::new(duration_sec, 0);
-    let start_time = Instant::now();
-    let mut run_count = 0;
-    let mut result = 0;
-
-    while Instant::now().duration_since(start_time) < duration {
-        result = f(input);
-        run_count += 1;
-        let result_cell = UnsafeCell::new(result);
-        unsafe {
-            std::ptr::write_volatile(result_cell.get(), result);
-        }
+    pub fn attempt(mut self, attempt: u32) -> Self {
+        self.attempt = attempt;
+        self
     }
 
-    let total_runtime = Instant::now().duration_since(start_time).as_secs_f64();
-    (result, run_count as f64 / total_runtime)
+    pub fn notes(mut self, notes: &'static str) -> Self {
+        self.notes = Some(notes);
+        self
+    }
 }
 
-pub fn print_sorted_results(
-    results: Vec<(
-        String,
-        String,
-        NaiveDate,
-        AICodeGenStatus,
-        usize,
-        f64,
-        String,
-    )>,
-) {
-    let mut grouped_results: HashMap<String, Vec<_>> = HashMap::new();
-
-    // Group results by function name
-    for result in results {
-        let function_name = result.1.clone();
-        grouped_results
-            .entry(function_name)
-            .or_insert_with(Vec::new)
-            .push(result);
-    }
+/// `candidate!(U64Function, "fibonacci", fibonacci, "2025-03-04", Ok)` is the
+/// fibonacci function's attempt at the task "fibonacci", generated on
+/// 2025-03-04 and declared Ok.
+macro_rules! candidate {
+    ($family:ty, $task:expr, $function:path, $date:literal, $status:ident) => {
+        $crate::common::Candidate::<$family>::new(
+            $task,
+            $function,
+            stringify!($function),
+            chrono::NaiveDate::parse_from_str($date, "%Y-%m-%d")
+                .expect("candidate date is not YYYY-MM-DD"),
+            $crate::common::AICodeGenStatus::$status,
+        )
+    };
+}
+pub(crate) use candidate;
 
-    for (function_name, function_results) in grouped_results {
-        let mut zero_time_results = vec![];
-        let mut non_zero_time_results = vec![];
+/// One row of the results table.
+#[derive(Debug)]
+pub struct ResultRow {
+    pub engine_name: String,
+    pub function_name: String,
+    pub date: NaiveDate,
+    pub status: AICodeGenStatus,
+    pub result: String,
+    pub summary: Summary,
+    /// None when the candidate did not run or its task's baseline did not finish.
+    pub speedup: Option<Speedup>,
+    pub is_baseline: bool,
+    /// Per-call hardware counts, when they were asked for.
+    pub counters: Option<Counters>,
+    /// Allocations per call, when they were asked for.
+    pub allocations: Option<Allocations>,
+    /// Per-call latency percentiles, when they were asked for.
+    pub latency: Option<Latency>,
+}
 
-        // Separate results with time 0 and non-zero time
-        for result in function_results {
-            if result.3 == AICodeGenStatus::Ok {
-                non_zero_time_results.push(result);
-            } else {
-                zero_time_results.push(result);
-            }
+impl ResultRow {
+    pub fn speedup_string(&self) -> String {
+        match (self.is_baseline, self.speedup) {
+            (true, _) => "-----".to_string(),
+            (false, Some(speedup)) => speedup.to_string(),
+            (false, None) => "none".to_string(),
         }
+    }
+}
 
-        // Sort non-zero time results by time (ascending)
-        non_zero_time_results.sort_by(|a, b| a.5.partial_cmp(&b.5).unwrap_or(Ordering::Equal));
-
-        // Combine the lists, putting zero time results at the top
-        zero_time_results.extend(non_zero_time_results);
+/// Times `f` on `inputs`, in rotation, as `timing` says.
+pub fn run_for_duration<T: Task>(f: T::Func, inputs: &[T::Input], timing: &Timing) -> Summary {
+    let duration = Duration::from_secs(timing.duration);
+    if timing.batched {
+        stats::measure_batched(call_in_rotation::<T>(f, inputs), duration, &Clock::detect())
+    } else {
+        stats::measure(call_in_rotation::<T>(f, inputs), duration).1
+    }
+}
 
-        // Create a table
-        let mut table = Table::new();
-
-        // Set the table format
-        table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
-
-        // Add a header row
-        table.set_titles(Row::new(vec![
-            Cell::new("AI Engine"),
-            Cell::new("Date"),
-            Cell::new("Status"),
-            Cell::new("Result"),
-            Cell::new("Iter/Sec"),
-            Cell::new("Speedup"),
-        ]));
-
-        // Add rows to the table
-        for result in zero_time_results {
-            table.add_row(Row::new(vec![
-                Cell::new(&result.0),
-                Cell::new(&format!("{}", result.2)),
-                Cell::new(&format!("{:?}", result.3)),
-                Cell::new(&result.4.to_string()),
-                Cell::new(&format!("{:.2}", result.5)),
-                Cell::new(&result.6),
-            ]));
-        }
+/// A closure calling `f` on the next of `inputs` each time. The function, the
+/// input and the result go through `black_box`, so that the calls can be
+/// neither hoisted out of a loop nor dropped as unused.
+pub fn call_in_rotation<T: Task>(f: T::Func, inputs: &[T::Input]) -> impl FnMut() {
+    let mut next = 0;
+    move || {
+        let input = &inputs[next];
+        next = if next + 1 == inputs.len() {
+            0
+        } else {
+            next + 1
+        };
+        black_box(T::call(black_box(f), black_box(input)));
+    }
+}
 
+pub fn print_sorted_results(results: &[ResultRow], output_format: Format) {
+    for (function_name, function_results) in sort_results(results) {
         // Print the table for this function name
-        println!("Results for function: {}", function_name);
-        table.printstd();
+        match output_format {
+            Format::Table => println!("Results for function: {}", function_name),
+            Format::Markdown => println!("### {}\n", function_name),
+        }
+        results_table(&function_results, output_format).printstd();
         println!("\n");
     }
 }
 
-pub fn print_sorted_results2(
-    results: Vec<(String, String, NaiveDate, AICodeGenStatus, u64, f64, String)>,
-) {
-    let mut grouped_results: HashMap<String, Vec<_>> = HashMap::new();
+/// Groups results by function name, in order of first appearance, and sorts
+/// each group: candidates that are not Ok first, then the Ok ones from slowest
+/// to fastest.
+pub fn sort_results(results: &[ResultRow]) -> Vec<(String, Vec<&ResultRow>)> {
+    let mut grouped_results: Vec<(String, Vec<&ResultRow>)> = Vec::new();
 
     // Group results by function name
     for result in results {
-        let function_name = result.1.clone();
-        grouped_results
-            .entry(function_name)
-            .or_insert_with(Vec::new)
-            .push(result);
+        match grouped_results
+            .iter_mut()
+            .find(|(name, _)| *name == result.function_name)
+        {
+            Some((_, group)) => group.push(result),
+            None => grouped_results.push((result.function_name.clone(), vec![result])),
+        }
     }
 
-    for (function_name, function_results) in grouped_results {
+    for (_, function_results) in grouped_results.iter_mut() {
         let mut zero_time_results = vec![];
         let mut non_zero_time_results = vec![];
 
         // Separate results with time 0 and non-zero time
-        for result in function_results {
-            if result.3 == AICodeGenStatus::Ok {
+        for result in function_results.drain(..) {
+            if result.status == AICodeGenStatus::Ok {
                 non_zero_time_results.push(result);
             } else {
                 zero_time_results.push(result);
@@ -236,42 +294,141 @@ pub fn print_sorted_results2(
         }
 
         // Sort non-zero time results by time (ascending)
-        non_zero_time_results.sort_by(|a, b| a.5.partial_cmp(&b.5).unwrap_or(Ordering::Equal));
+        non_zero_time_results.sort_by(|a, b| {
+            a.summary
+                .median
+                .partial_cmp(&b.summary.median)
+                .unwrap_or(Ordering::Equal)
+        });
 
         // Combine the lists, putting zero time results at the top
         zero_time_results.extend(non_zero_time_results);
+        *function_results = zero_time_results;
+    }
+
+    grouped_results
+}
+
+/// Builds the results table of one function.
+pub fn results_table(results: &[&ResultRow], output_format: Format) -> Table {
+    // Create a table
+    let mut table = Table::new();
+
+    // Set the table format
+    table.set_format(table_format(output_format));
+
+    // Add a header row, with the counter columns if any row has counters
+    let with_counters = results
+        .iter()
+        .any(|result| result.counters.is_some_and(|counters| !counters.is_empty()));
+    let mut titles = vec![
+        Cell::new("AI Engine"),
+        Cell::new("Date"),
+        Cell::new("Status"),
+        Cell::new("Result"),
+        Cell::new("Iter/Sec"),
+        Cell::new("Mean"),
+        Cell::new("StdDev"),
+        Cell::new("Min"),
+        Cell::new("95% CI"),
+        Cell::new("Speedup"),
+    ];
+    let with_allocations = results.iter().any(|result| result.allocations.is_some());
+    let with_latency = results.iter().any(|result| result.latency.is_some());
+    if with_counters {
+        titles.extend(
+            [
+                "Cycles",
+                "Instructions",
+                "IPC",
+                "Branch Misses",
+                "L1d Misses",
+                "LLC Misses",
+            ]
+            .map(Cell::new),
+        );
+    }
+    if with_allocations {
+        titles.extend(["Allocs/Call", "Bytes/Call", "Peak Heap"].map(Cell::new));
+    }
+    if with_latency {
+        titles.extend(["p50", "p90", "p99", "p99.9", "Max"].map(Cell::new));
+    }
+    table.set_titles(Row::new(titles));
 
-        // Create a table
-        let mut table = Table::new();
-
-        // Set the table format
-        table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
-
-        // Add a header row
-        table.set_titles(Row::new(vec![
-            Cell::new("AI Engine"),
-            Cell::new("Date"),
-            Cell::new("Status"),
-            Cell::new("Result"),
-            Cell::new("Iter/Sec"),
-            Cell::new("Speedup"),
-        ]));
-
-        // Add rows to the table
-        for result in zero_time_results {
-            table.add_row(Row::new(vec![
-                Cell::new(&result.0),
-                Cell::new(&format!("{}", result.2)),
-                Cell::new(&format!("{:?}", result.3)),
-                Cell::new(&result.4.to_string()),
-                Cell::new(&format!("{:.2}", result.5)),
-                Cell::new(&result.6),
-            ]));
+    // Add rows to the table
+    for result in results {
+        let mut cells = vec![
+            Cell::new(&result.engine_name),
+            Cell::new(&format!("{}", result.date)),
+            Cell::new(&format!("{:?}", result.status)),
+            Cell::new(&result.result),
+            Cell::new(&format!("{:.2}", result.summary.median)),
+            Cell::new(&format!("{:.2}", result.summary.mean)),
+            Cell::new(&format!("{:.2}", result.summary.std_dev)),
+            Cell::new(&format!("{:.2}", result.summary.min)),
+            Cell::new(&result.summary.ci95_string()),
+            Cell::new(&result.speedup_string()),
+        ];
+        if with_counters {
+            let counters = result.counters.unwrap_or_default();
+            cells.extend(
+                [
+                    perf::format_count(counters.cycles),
+                    perf::format_count(counters.instructions),
+                    counters
+                        .ipc()
+                        .map_or("-".to_string(), |ipc| format!("{:.2}", ipc)),
+                    perf::format_count(counters.branch_misses),
+                    perf::format_count(counters.l1d_misses),
+                    perf::format_count(counters.llc_misses),
+                ]
+                .map(|value| Cell::new(&value)),
+            );
+        }
+        if with_allocations {
+            cells.extend(
+                match result.allocations {
+                    Some(allocations) => [
+                        format!("{:.1}", allocations.per_call),
+                        format!("{:.0}", allocations.bytes_per_call),
+                        allocations.peak_bytes.to_string(),
+                    ],
+                    None => ["-".to_string(), "-".to_string(), "-".to_string()],
+                }
+                .map(|value| Cell::new(&value)),
+            );
         }
+        if with_latency {
+            cells.extend(match &result.latency {
+                Some(latency) => [
+                    latency.p50_ns,
+                    latency.p90_ns,
+                    latency.p99_ns,
+                    latency.p999_ns,
+                    latency.max_ns,
+                ]
+                .map(|nanos| Cell::new(&latency::format_nanos(nanos))),
+                None => ["-"; 5].map(Cell::new),
+            });
+        }
+        table.add_row(Row::new(cells));
+    }
 
-        // Print the table for this function name
-        println!("Results for function: {}", function_name);
-        table.printstd();
-        println!("\n");
+    table
+}
+
+pub fn table_format(output_format: Format) -> format::TableFormat {
+    match output_format {
+        Format::Table => *format::consts::FORMAT_NO_LINESEP_WITH_TITLE,
+        Format::Markdown => format::FormatBuilder::new()
+            .column_separator('|')
+            .borders('|')
+            .separator(
+                format::LinePosition::Title,
+                format::LineSeparator::new('-', '|', '|', '|'),
+            )
+            .padding(1, 1)
+            .build(),
     }
 }
@@ -0,0 +1,195 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+// Timings move with the machine: the scheduler migrating the benchmark between
+// cores, the frequency governor ramping up and down, turbo boost depending on
+// temperature, other processes. `run --pin-cpu N` makes every child run on
+// core N only (sched_setaffinity) and `--high-priority` lowers the harness'
+// nice value, which the children inherit. Before timing, the governor, turbo
+// and load are read from sysfs and procfs, shown, recorded in the exports, and
+// whatever makes timings noisy is warned about. All of it is Linux only; other
+// systems report nothing and cannot pin.
+
+/// Nice value asked for by --high-priority; lowering it needs CAP_SYS_NICE.
+const HIGH_PRIORITY_NICE: i32 = -20;
+
+/// One-minute load average above which other work is taken to compete with
+/// the benchmark.
+const NOISY_LOAD: f64 = 1.0;
+
+/// State of the machine at the start of a run.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Environment {
+    pub pinned_cpu: Option<usize>,
+    pub high_priority: bool,
+    /// Frequency governors in use, e.g. ["performance"]; empty when unknown.
+    pub governors: Vec<String>,
+    /// None when the machine does not say.
+    pub turbo: Option<bool>,
+    pub load_average: Option<f64>,
+    pub hypervisor: bool,
+}
+
+impl Environment {
+    /// Reads the state of the CPU the benchmark runs on (all of them when not pinned).
+    pub fn detect(pinned_cpu: Option<usize>, high_priority: bool) -> Self {
+        let cpus: Vec<usize> = match pinned_cpu {
+            Some(cpu) => vec![cpu],
+            None => (0..std::thread::available_parallelism().map_or(1, |n| n.get())).collect(),
+        };
+        let mut governors: Vec<String> = cpus
+            .iter()
+            .filter_map(|cpu| {
+                read_sys(&format!(
+                    "/sys/devices/system/cpu/cpu{}/cpufreq/scaling_governor",
+                    cpu
+                ))
+            })
+            .collect();
+        governors.sort();
+        governors.dedup();
+
+        Environment {
+            pinned_cpu,
+            high_priority,
+            governors,
+            turbo: turbo(),
+            load_average: read_sys("/proc/loadavg")
+                .and_then(|load| load.split(' ').next()?.parse().ok()),
+            hypervisor: read_sys("/proc/cpuinfo").is_some_and(|cpuinfo| {
+                cpuinfo.lines().any(|line| {
+                    line.starts_with("flags") && line.split(' ').any(|f| f == "hypervisor")
+                })
+            }),
+        }
+    }
+
+    /// One line describing the environment, e.g. "pinned to CPU 3, governor
+    /// performance, turbo off, load 0.12".
+    pub fn describe(&self) -> String {
+        let mut parts = vec![match self.pinned_cpu {
+            Some(cpu) => format!("pinned to CPU {}", cpu),
+            None => "not pinned".to_string(),
+        }];
+        if self.high_priority {
+            parts.push("high priority".to_string());
+        }
+        parts.push(match self.governors.as_slice() {
+            [] => "governor unknown".to_string(),
+            governors => format!("governor {}", governors.join("/")),
+        });
+        parts.push(match self.turbo {
+            Some(true) => "turbo on".to_string(),
+            Some(false) => "turbo off".to_string(),
+            None => "turbo unknown".to_string(),
+        });
+        if let Some(load) = self.load_average {
+            parts.push(format!("load {:.2}", load));
+        }
+        if self.hypervisor {
+            parts.push("virtual machine".to_string());
+        }
+        parts.join(", ")
+    }
+
+    /// What in the environment makes timings less repeatable.
+    pub fn noise(&self) -> Vec<String> {
+        let mut noise = Vec::new();
+        if self
+            .governors
+            .iter()
+            .any(|governor| governor != "performance")
+        {
+            noise.push(format!(
+                "the CPU frequency governor is {} rather than performance, so the clock speed varies",
+                self.governors.join("/")
+            ));
+        }
+        if self.turbo == Some(true) {
+            noise.push("turbo boost is on, so the clock speed depends on temperature".to_string());
+        }
+        if let Some(load) = self.load_average.filter(|load| *load > NOISY_LOAD) {
+            noise.push(format!(
+                "the load average is {:.2}, so other processes compete for the CPU",
+                load
+            ));
+        }
+        if self.hypervisor {
+            noise
+                .push("running in a virtual machine, which shares the CPU with others".to_string());
+        }
+        noise
+    }
+}
+
+fn read_sys(path: &str) -> Option<String> {
+    fs::read_to_string(path)
+        .ok()
+        .map(|value| value.trim().to_string())
+}
+
+/// Whether turbo boost is on, from intel_pstate or the generic cpufreq boost switch.
+fn turbo() -> Option<bool> {
+    if let Some(no_turbo) = read_sys("/sys/devices/system/cpu/intel_pstate/no_turbo") {
+        return Some(no_turbo == "0");
+    }
+    read_sys("/sys/devices/system/cpu/cpufreq/boost").map(|boost| boost == "1")
+}
+
+/// Restricts the calling thread, and the processes it starts, to `cpu`.
+#[cfg(target_os = "linux")]
+pub fn pin_to_cpu(cpu: usize) -> Result<(), String> {
+    if cpu >= libc::CPU_SETSIZE as usize {
+        return Err(format!("CPU {} does not exist", cpu));
+    }
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(cpu, &mut set);
+        if libc::sched_setaffinity(0, size_of::<libc::cpu_set_t>(), &set) != 0 {
+            return Err(format!(
+                "cannot pin to CPU {}: {}",
+                cpu,
+                std::io::Error::last_os_error()
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn pin_to_cpu(_cpu: usize) -> Result<(), String> {
+    Err("pinning to a CPU is only supported on Linux".to_string())
+}
+
+/// Whether the calling thread may run on `cpu`.
+#[cfg(target_os = "linux")]
+pub fn cpu_allowed(cpu: usize) -> bool {
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        cpu < libc::CPU_SETSIZE as usize
+            && libc::sched_getaffinity(0, size_of::<libc::cpu_set_t>(), &mut set) == 0
+            && libc::CPU_ISSET(cpu, &set)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn cpu_allowed(_cpu: usize) -> bool {
+    false
+}
+
+/// Lowers this process' nice value, which the processes it starts inherit.
+#[cfg(unix)]
+pub fn raise_priority() -> Result<(), String> {
+    if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, HIGH_PRIORITY_NICE) } != 0 {
+        return Err(format!(
+            "cannot raise the priority: {} (needs root or CAP_SYS_NICE)",
+            std::io::Error::last_os_error()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn raise_priority() -> Result<(), String> {
+    Err("raising the priority is only supported on Unix".to_string())
+}
@@ -0,0 +1,297 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::cli::Timing;
+use crate::common::ResultRow;
+use crate::environment::Environment;
+use crate::fingerprint::{self, Build};
+use crate::heap::Allocations;
+use crate::latency::Latency;
+use crate::perf::Counters;
+use crate::stats::{Speedup, Summary};
+
+// The exported schema is kept separate from the harness' own types so that it
+// only changes on purpose. Bump SCHEMA_VERSION whenever a field is renamed or
+// removed; adding a field does not need a bump.
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Everything one run of the harness produced.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Report {
+    pub schema_version: u32,
+    /// RFC 3339, UTC.
+    pub run_timestamp: String,
+    pub git_revision: String,
+    pub host: Host,
+    /// How the harness was compiled.
+    #[serde(default)]
+    pub build: Build,
+    pub duration_sec: u64,
+    /// None when every task was timed on its benchmark input.
+    pub input_size: Option<u64>,
+    /// Inputs the candidates were timed on in rotation; 1 for the usual input only.
+    #[serde(default = "default_input_pool")]
+    pub input_pool: usize,
+    /// "instant", or "batched-tsc" or "batched-instant" for runs with --batched.
+    #[serde(default = "default_timer")]
+    pub timer: String,
+    /// CPU pinning, governor, turbo and load at the start of the run.
+    #[serde(default)]
+    pub environment: Environment,
+    pub results: Vec<Record>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Host {
+    pub hostname: String,
+    pub os: String,
+    pub arch: String,
+    pub cpus: usize,
+    #[serde(default)]
+    pub cpu_model: String,
+    /// Kernel release; empty outside Linux.
+    #[serde(default)]
+    pub kernel: String,
+    /// SIMD extensions the CPU has.
+    #[serde(default)]
+    pub simd: Vec<String>,
+}
+
+/// One candidate (or baseline) of one task.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Record {
+    pub engine: String,
+    pub function: String,
+    /// Date the candidate was generated, YYYY-MM-DD.
+    pub date: String,
+    pub status: String,
+    pub result: String,
+    pub is_baseline: bool,
+    /// Median of the samples, the number shown as Iter/Sec.
+    pub iterations_per_sec: f64,
+    pub speedup: Option<Speedup>,
+    pub samples: Summary,
+    /// Per-call hardware counts, for runs with --counters.
+    pub counters: Option<Counters>,
+    /// Allocations per call, for runs with --allocations.
+    pub allocations: Option<Allocations>,
+    /// Per-call latency percentiles and histogram, for runs with --latency.
+    pub latency: Option<Latency>,
+}
+
+impl Report {
+    pub fn new(timing: &Timing, environment: Environment, rows: &[ResultRow]) -> Self {
+        Report {
+            schema_version: SCHEMA_VERSION,
+            run_timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            git_revision: env!("GIT_REVISION").to_string(),
+            host: Host::detect(),
+            build: Build::current(),
+            duration_sec: timing.duration,
+            input_size: timing.input_size,
+            input_pool: timing.input_pool,
+            timer: timing.timer(),
+            environment,
+            results: rows.iter().map(Record::from).collect(),
+        }
+    }
+
+    pub fn read_json(path: &Path) -> Result<Self, String> {
+        let json = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        serde_json::from_str(&json).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn write_json(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)? + "\n")
+    }
+
+    /// Writes one line per record, with the run-level fields repeated on every line.
+    pub fn write_csv(&self, path: &Path) -> io::Result<()> {
+        let mut csv = String::from(
+            "engine,function,date,status,result,is_baseline,iterations_per_sec,\
+             speedup,speedup_low,speedup_high,sample_count,median,mean,std_dev,min,\
+             ci95_low,ci95_high,run_timestamp,git_revision,hostname,os,arch,cpus,\
+             cpu_model,kernel,simd,rustc,profile,opt_level,target_cpu,target_features,lto,\
+             codegen_units,panic,\
+             duration_sec,input_size,input_pool,timer,pinned_cpu,governors,turbo,load_average,\
+             cycles,instructions,ipc,branch_misses,\
+             l1d_misses,llc_misses,allocations_per_call,bytes_per_call,peak_heap_bytes,\
+             p50_ns,p90_ns,p99_ns,p999_ns,max_ns\n",
+        );
+
+        for record in &self.results {
+            let speedup = |f: fn(&Speedup) -> f64| record.speedup.as_ref().map(f);
+            let counters = record.counters.unwrap_or_default();
+            let latency = |f: fn(&Latency) -> u64| record.latency.as_ref().map(f);
+            let fields = [
+                csv_field(&record.engine),
+                csv_field(&record.function),
+                csv_field(&record.date),
+                csv_field(&record.status),
+                csv_field(&record.result),
+                record.is_baseline.to_string(),
+                record.iterations_per_sec.to_string(),
+                optional(speedup(|s| s.ratio)),
+                optional(speedup(|s| s.low)),
+                optional(speedup(|s| s.high)),
+                record.samples.samples.to_string(),
+                record.samples.median.to_string(),
+                record.samples.mean.to_string(),
+                record.samples.std_dev.to_string(),
+                record.samples.min.to_string(),
+                record.samples.ci95_low.to_string(),
+                record.samples.ci95_high.to_string(),
+                csv_field(&self.run_timestamp),
+                csv_field(&self.git_revision),
+                csv_field(&self.host.hostname),
+                csv_field(&self.host.os),
+                csv_field(&self.host.arch),
+                self.host.cpus.to_string(),
+                csv_field(&self.host.cpu_model),
+                csv_field(&self.host.kernel),
+                csv_field(&self.host.simd.join(" ")),
+                csv_field(&self.build.rustc),
+                csv_field(&self.build.profile),
+                csv_field(&self.build.opt_level),
+                csv_field(&self.build.target_cpu),
+                csv_field(&self.build.target_features.join(" ")),
+                csv_field(&self.build.lto),
+                csv_field(&self.build.codegen_units),
+                csv_field(&self.build.panic),
+                self.duration_sec.to_string(),
+                optional(self.input_size),
+                self.input_pool.to_string(),
+                csv_field(&self.timer),
+                optional(self.environment.pinned_cpu),
+                csv_field(&self.environment.governors.join("/")),
+                optional(self.environment.turbo),
+                optional(self.environment.load_average),
+                optional(counters.cycles),
+                optional(counters.instructions),
+                optional(counters.ipc()),
+                optional(counters.branch_misses),
+                optional(counters.l1d_misses),
+                optional(counters.llc_misses),
+                optional(record.allocations.map(|a| a.per_call)),
+                optional(record.allocations.map(|a| a.bytes_per_call)),
+                optional(record.allocations.map(|a| a.peak_bytes)),
+                optional(latency(|l| l.p50_ns)),
+                optional(latency(|l| l.p90_ns)),
+                optional(latency(|l| l.p99_ns)),
+                optional(latency(|l| l.p999_ns)),
+                optional(latency(|l| l.max_ns)),
+            ];
+            csv.push_str(&fields.join(","));
+            csv.push('\n');
+        }
+
+        fs::write(path, csv)
+    }
+}
+
+impl From<&ResultRow> for Record {
+    fn from(row: &ResultRow) -> Self {
+        Record {
+            engine: row.engine_name.clone(),
+            function: row.function_name.clone(),
+            date: row.date.to_string(),
+            status: row.status.to_string(),
+            result: row.result.clone(),
+            is_baseline: row.is_baseline,
+            iterations_per_sec: row.summary.median,
+            speedup: row.speedup,
+            samples: row.summary,
+            counters: row.counters,
+            allocations: row.allocations,
+            latency: row.latency.clone(),
+        }
+    }
+}
+
+impl TryFrom<&Record> for ResultRow {
+    type Error = String;
+
+    fn try_from(record: &Record) -> Result<Self, Self::Error> {
+        Ok(ResultRow {
+            engine_name: record.engine.clone(),
+            function_name: record.function.clone(),
+            date: record
+                .date
+                .parse()
+                .map_err(|e| format!("bad date '{}': {}", record.date, e))?,
+            status: record.status.parse()?,
+            result: record.result.clone(),
+            summary: record.samples,
+            speedup: record.speedup,
+            is_baseline: record.is_baseline,
+            counters: record.counters,
+            allocations: record.allocations,
+            latency: record.latency.clone(),
+        })
+    }
+}
+
+impl Host {
+    pub fn detect() -> Self {
+        Host {
+            hostname: hostname(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            cpus: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            cpu_model: fingerprint::cpu_model(),
+            kernel: fingerprint::kernel(),
+            simd: fingerprint::simd_features(),
+        }
+    }
+
+    /// One line, e.g. "ci-runner: AMD EPYC 7763, 4 cpus, linux 6.8.0 x86_64, SIMD sse2 ... avx2".
+    pub fn describe(&self) -> String {
+        format!(
+            "{}: {}, {} cpus, {} {} {}, SIMD {}",
+            self.hostname,
+            self.cpu_model,
+            self.cpus,
+            self.os,
+            self.kernel,
+            self.arch,
+            match self.simd.as_slice() {
+                [] => "unknown".to_string(),
+                simd => simd.join(" "),
+            }
+        )
+    }
+}
+
+fn hostname() -> String {
+    std::env::var("COMPUTERNAME")
+        .ok()
+        .or_else(|| fs::read_to_string("/proc/sys/kernel/hostname").ok())
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .map(|name| name.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Input pool of records written before it was recorded.
+pub fn default_input_pool() -> usize {
+    1
+}
+
+/// Timer of records written before it was recorded.
+pub fn default_timer() -> String {
+    "instant".to_string()
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
@@ -0,0 +1,158 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+// A speedup measured on one machine and build says little about another: a
+// candidate that autovectorizes wins with AVX2 and target-cpu=native and not
+// without, LTO can inline the baseline's helpers. Every export and history
+// record carries the CPU model, kernel and SIMD features of the host (in
+// export::Host) and how the harness was compiled (Build, from the values
+// build.rs records), and `run` prints both above the tables.
+
+/// How the harness binary was compiled.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Build {
+    /// Output of rustc --version.
+    pub rustc: String,
+    /// Cargo profile, e.g. "release".
+    pub profile: String,
+    pub opt_level: String,
+    /// "generic" when no -C target-cpu was given.
+    pub target_cpu: String,
+    /// Target features enabled at compile time, which the compiler may use anywhere.
+    pub target_features: Vec<String>,
+    /// As in the profile: "default", "false", "thin", "fat", "off"...
+    pub lto: String,
+    pub codegen_units: String,
+    /// "default", "unwind" or "abort".
+    pub panic: String,
+}
+
+impl Build {
+    pub fn current() -> Self {
+        Build {
+            rustc: env!("BUILD_RUSTC").to_string(),
+            profile: env!("BUILD_PROFILE").to_string(),
+            opt_level: env!("BUILD_OPT_LEVEL").to_string(),
+            target_cpu: env!("BUILD_TARGET_CPU").to_string(),
+            target_features: env!("BUILD_TARGET_FEATURES")
+                .split(',')
+                .filter(|feature| !feature.is_empty())
+                .map(str::to_string)
+                .collect(),
+            lto: env!("BUILD_LTO").to_string(),
+            codegen_units: env!("BUILD_CODEGEN_UNITS").to_string(),
+            panic: env!("BUILD_PANIC").to_string(),
+        }
+    }
+
+    /// One line, e.g. "rustc 1.85.0 (...), release (opt-level 3), target-cpu
+    /// native, LTO fat, codegen-units 1, panic abort".
+    pub fn describe(&self) -> String {
+        format!(
+            "{}, {} (opt-level {}), target-cpu {}, LTO {}, codegen-units {}, panic {}",
+            self.rustc,
+            self.profile,
+            self.opt_level,
+            self.target_cpu,
+            self.lto,
+            self.codegen_units,
+            self.panic
+        )
+    }
+}
+
+/// The CPU's name, from /proc/cpuinfo or the cpuid brand string.
+pub fn cpu_model() -> String {
+    let from_cpuinfo = fs::read_to_string("/proc/cpuinfo")
+        .ok()
+        .and_then(|cpuinfo| {
+            cpuinfo.lines().find_map(|line| {
+                let (key, value) = line.split_once(':')?;
+                matches!(key.trim(), "model name" | "Model" | "Hardware" | "cpu")
+                    .then(|| value.trim().to_string())
+            })
+        });
+    from_cpuinfo
+        .or_else(brand_string)
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+#[cfg(target_arch = "x86_64")]
+fn brand_string() -> Option<String> {
+    use std::arch::x86_64::__cpuid;
+
+    if __cpuid(0x8000_0000).eax < 0x8000_0004 {
+        return None;
+    }
+    let bytes: Vec<u8> = (0x8000_0002..=0x8000_0004)
+        .flat_map(|leaf| {
+            let registers = __cpuid(leaf);
+            [registers.eax, registers.ebx, registers.ecx, registers.edx]
+        })
+        .flat_map(u32::to_le_bytes)
+        .collect();
+    let brand = String::from_utf8_lossy(&bytes)
+        .trim_matches(|c: char| c == '\0' || c.is_whitespace())
+        .to_string();
+    (!brand.is_empty()).then_some(brand)
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn brand_string() -> Option<String> {
+    None
+}
+
+/// Kernel release on Linux, empty elsewhere.
+pub fn kernel() -> String {
+    fs::read_to_string("/proc/sys/kernel/osrelease")
+        .map(|release| release.trim().to_string())
+        .unwrap_or_default()
+}
+
+/// SIMD extensions the CPU has, whether or not the build uses them.
+#[cfg(target_arch = "x86_64")]
+pub fn simd_features() -> Vec<String> {
+    let detected = [
+        ("sse2", is_x86_feature_detected!("sse2")),
+        ("sse3", is_x86_feature_detected!("sse3")),
+        ("ssse3", is_x86_feature_detected!("ssse3")),
+        ("sse4.1", is_x86_feature_detected!("sse4.1")),
+        ("sse4.2", is_x86_feature_detected!("sse4.2")),
+        ("popcnt", is_x86_feature_detected!("popcnt")),
+        ("avx", is_x86_feature_detected!("avx")),
+        ("avx2", is_x86_feature_detected!("avx2")),
+        ("fma", is_x86_feature_detected!("fma")),
+        ("bmi2", is_x86_feature_detected!("bmi2")),
+        ("avx512f", is_x86_feature_detected!("avx512f")),
+        ("avx512bw", is_x86_feature_detected!("avx512bw")),
+        ("avx512vl", is_x86_feature_detected!("avx512vl")),
+    ];
+    names(&detected)
+}
+
+#[cfg(target_arch = "aarch64")]
+pub fn simd_features() -> Vec<String> {
+    use std::arch::is_aarch64_feature_detected;
+
+    let detected = [
+        ("neon", is_aarch64_feature_detected!("neon")),
+        ("dotprod", is_aarch64_feature_detected!("dotprod")),
+        ("sve", is_aarch64_feature_detected!("sve")),
+        ("sve2", is_aarch64_feature_detected!("sve2")),
+    ];
+    names(&detected)
+}
+
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+pub fn simd_features() -> Vec<String> {
+    Vec::new()
+}
+
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+fn names(detected: &[(&str, bool)]) -> Vec<String> {
+    detected
+        .iter()
+        .filter(|(_, present)| *present)
+        .map(|(name, _)| name.to_string())
+        .collect()
+}
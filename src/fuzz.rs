@@ -0,0 +1,519 @@
+use prettytable::{Cell, Row, Table};
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::time::Duration;
+
+use crate::cli::FuzzArgs;
+use crate::common::CandidateInfo;
+use crate::isolate::{self, Outcome};
+use crate::task::{self, Task};
+
+// The verification inputs are a handful of hand-picked values, so a candidate
+// that is wrong on an empty string or a small n can still come out Ok. The
+// fuzzer runs each candidate and the baseline on random inputs from the task
+// (see Task::random_input) and, at the first disagreement, shrinks the input
+// while they still disagree, so the report shows the simplest input found.
+// Case i of a run only depends on the seed and i, so a run is reproduced with
+// --seed. Like `run`, each candidate is fuzzed in a child process, which also
+// catches candidates that hang or crash on some input.
+
+/// Hidden subcommand the harness re-executes itself with to fuzz one candidate.
+pub const CHILD_COMMAND: &str = "__fuzz-candidate";
+
+/// Simpler inputs tried, at most, while shrinking a counterexample.
+const MAX_SHRINK_STEPS: usize = 1000;
+
+/// SplitMix64: small, fast and the same on every platform and Rust version,
+/// which is what reproducing a run from its seed needs.
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    /// Generator of case `case` of the run seeded with `seed`.
+    pub fn for_case(seed: u64, case: u64) -> Self {
+        Rng::new(seed ^ case.wrapping_mul(0xD1B5_4A32_D192_ED03))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0..n`, n > 0.
+    pub fn below(&mut self, n: u64) -> u64 {
+        ((self.next_u64() as u128 * n as u128) >> 64) as u64
+    }
+
+    /// True with probability numerator / denominator.
+    pub fn chance(&mut self, numerator: u64, denominator: u64) -> bool {
+        self.below(denominator) < numerator
+    }
+
+    pub fn pick<'a, X>(&mut self, items: &'a [X]) -> &'a X {
+        &items[self.below(items.len() as u64) as usize]
+    }
+}
+
+pub enum Finding {
+    Passed,
+    Disagrees {
+        /// The call, e.g. count_primes(0).
+        input: String,
+        candidate: String,
+        baseline: String,
+        shrinks: usize,
+    },
+    /// The child hung, crashed or failed, on `input` when it is known.
+    Failed {
+        input: Option<String>,
+        reason: String,
+    },
+}
+
+pub struct Report {
+    pub engine_name: String,
+    pub task_name: String,
+    pub function_name: &'static str,
+    pub cases: u64,
+    pub finding: Finding,
+}
+
+/// Fuzzes every selected candidate of `modules` but the baseline, each in a
+/// child process.
+pub fn fuzz_family<T: Task>(
+    args: &FuzzArgs,
+    seed: u64,
+    family: &str,
+    tasks: &[T],
+    modules: &[CandidateInfo<T>],
+) -> Vec<Report> {
+    let mut reports = Vec::new();
+
+    for (i, module) in modules.iter().enumerate().skip(1) {
+        if !args.filter.engine_selected(&module.engine_name) {
+            continue;
+        }
+        for (j, candidate) in module.candidates.iter().enumerate() {
+            if !args.filter.task_selected(candidate.task) || !candidate.status.has_code() {
+                continue;
+            }
+            let task = task::find(tasks, candidate.task).expect("checked at startup");
+            eprintln!(
+                "fuzzing {} / {}",
+                module.engine_name, candidate.function_name
+            );
+
+            let child_args = [
+                CHILD_COMMAND.to_string(),
+                family.to_string(),
+                i.to_string(),
+                j.to_string(),
+                seed.to_string(),
+                args.cases.to_string(),
+            ];
+            let finding = match isolate::run_child(&child_args, Duration::from_secs(args.timeout)) {
+                Ok(output) => parse_output(&output).unwrap_or_else(|| Finding::Failed {
+                    input: None,
+                    reason: "malformed child output".to_string(),
+                }),
+                Err((outcome, output)) => Finding::Failed {
+                    input: last_input(task, seed, &output),
+                    reason: match outcome {
+                        Outcome::Timeout => format!("did not finish in {} s", args.timeout),
+                        Outcome::Panicked(message) => format!("panicked: {}", message),
+                        Outcome::Crashed(reason) => reason,
+                        Outcome::Failed(reason) => reason,
+                        Outcome::Finished { .. } => unreachable!(),
+                    },
+                },
+            };
+
+            reports.push(Report {
+                engine_name: module.engine_name.clone(),
+                task_name: candidate.task.to_string(),
+                function_name: candidate.function_name,
+                cases: args.cases,
+                finding,
+            });
+        }
+    }
+
+    reports
+}
+
+pub fn print_reports(reports: &[Report]) {
+    let mut table = Table::new();
+    table.set_format(*prettytable::format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+    table.set_titles(Row::new(vec![
+        Cell::new("AI Engine"),
+        Cell::new("Task"),
+        Cell::new("Function"),
+        Cell::new("Verdict"),
+        Cell::new("Counterexample"),
+        Cell::new("Candidate"),
+        Cell::new("Baseline"),
+    ]));
+
+    for report in reports {
+        let last_tried;
+        let (verdict, input, candidate, baseline) = match &report.finding {
+            Finding::Passed => (format!("passed {} cases", report.cases), "", "", ""),
+            Finding::Disagrees {
+                input,
+                candidate,
+                baseline,
+                shrinks,
+            } => (
+                format!("disagrees (shrunk {} times)", shrinks),
+                input.as_str(),
+                candidate.as_str(),
+                baseline.as_str(),
+            ),
+            Finding::Failed { input, reason } => {
+                last_tried = input
+                    .as_ref()
+                    .map_or(String::new(), |input| format!("last tried: {}", input));
+                (reason.clone(), last_tried.as_str(), "", "")
+            }
+        };
+        table.add_row(Row::new(vec![
+            Cell::new(&report.engine_name),
+            Cell::new(&report.task_name),
+            Cell::new(report.function_name),
+            Cell::new(&verdict),
+            Cell::new(input),
+            Cell::new(candidate),
+            Cell::new(baseline),
+        ]));
+    }
+
+    table.printstd();
+}
+
+/// Entry point of the child process: fuzzes one candidate and writes what it
+/// found to stdout, one `isolate::report` line per field. Before each input it
+/// reports "case i" or, while shrinking, "input call", so that the parent knows
+/// the input if the candidate never returns.
+pub fn child_main<T: Task>(
+    tasks: &[T],
+    modules: &[CandidateInfo<T>],
+    module: usize,
+    function: usize,
+    seed: u64,
+    cases: u64,
+) {
+    // panics are caught and reported per input
+    panic::set_hook(Box::new(|_| {}));
+
+    let candidate = &modules[module].candidates[function];
+    let task = task::find(tasks, candidate.task).expect("candidate does not belong to any task");
+    let baseline = &modules[0];
+    let reference = baseline.candidates[baseline
+        .position(task.name())
+        .expect("task has no baseline")]
+    .function;
+
+    for case in 0..cases {
+        isolate::report("case", case);
+        let input = task.random_input(&mut Rng::for_case(seed, case));
+        let Some(outputs) = disagreement(task, candidate.function, reference, &input) else {
+            continue;
+        };
+
+        let shrunk = shrink(
+            task,
+            candidate.function,
+            reference,
+            input,
+            outputs,
+            MAX_SHRINK_STEPS,
+        );
+        isolate::report(
+            "counterexample",
+            T::call_source(task.function_name(), &shrunk.input),
+        );
+        isolate::report("candidate", shrunk.outputs.0);
+        isolate::report("baseline", shrunk.outputs.1);
+        isolate::report("shrinks", shrunk.shrinks);
+        return;
+    }
+    isolate::report("passed", cases);
+}
+
+/// A counterexample after shrinking.
+struct Shrunk<I> {
+    input: I,
+    /// What the candidate and the baseline returned on it.
+    outputs: (String, String),
+    /// Simpler inputs found that still disagree.
+    shrinks: usize,
+    /// Simpler inputs tried.
+    tried: usize,
+}
+
+/// Replaces `input` with the first simpler one the candidate still disagrees
+/// on, as long as there is one and fewer than `max_tries` were tried,
+/// reporting each before calling the candidate on it.
+fn shrink<T: Task>(
+    task: &T,
+    function: T::Func,
+    baseline: T::Func,
+    input: T::Input,
+    outputs: (String, String),
+    max_tries: usize,
+) -> Shrunk<T::Input> {
+    let mut shrunk = Shrunk {
+        input,
+        outputs,
+        shrinks: 0,
+        tried: 0,
+    };
+    'shrinking: loop {
+        for simpler in task.shrink(&shrunk.input) {
+            if shrunk.tried == max_tries {
+                break 'shrinking;
+            }
+            shrunk.tried += 1;
+            isolate::report("input", T::call_source(task.function_name(), &simpler));
+            if let Some(found) = disagreement(task, function, baseline, &simpler) {
+                shrunk.input = simpler;
+                shrunk.outputs = found;
+                shrunk.shrinks += 1;
+                continue 'shrinking;
+            }
+        }
+        break;
+    }
+    shrunk
+}
+
+/// What the candidate and the baseline returned on `input`, if they differ.
+/// Inputs the baseline panics on are not ones the task is defined for.
+fn disagreement<T: Task>(
+    task: &T,
+    function: T::Func,
+    baseline: T::Func,
+    input: &T::Input,
+) -> Option<(String, String)> {
+    let expected = panic::catch_unwind(AssertUnwindSafe(|| T::call(baseline, input))).ok()?;
+    match panic::catch_unwind(AssertUnwindSafe(|| T::call(function, input))) {
+        Ok(output) if task.outputs_match(&output, &expected) => None,
+        Ok(output) => Some((task.format_output(&output), task.format_output(&expected))),
+        Err(payload) => Some((
+            format!("panic: {}", panic_message(payload.as_ref())),
+            task.format_output(&expected),
+        )),
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    let message = if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.as_str()
+    } else {
+        "unknown panic"
+    };
+    message.replace('\n', " ")
+}
+
+fn parse_output(output: &str) -> Option<Finding> {
+    let mut input = None;
+    let mut candidate = None;
+    let mut baseline = None;
+    let mut shrinks = None;
+
+    for (key, value) in output.lines().filter_map(isolate::report_line) {
+        match key {
+            "case" | "input" => {}
+            "passed" => return Some(Finding::Passed),
+            "counterexample" => input = Some(value.to_string()),
+            "candidate" => candidate = Some(value.to_string()),
+            "baseline" => baseline = Some(value.to_string()),
+            "shrinks" => shrinks = Some(value.parse().ok()?),
+            _ => return None,
+        }
+    }
+
+    Some(Finding::Disagrees {
+        input: input?,
+        candidate: candidate?,
+        baseline: baseline?,
+        shrinks: shrinks?,
+    })
+}
+
+/// The input the child was on when it stopped, from its last "case" or "input" line.
+fn last_input<T: Task>(task: &T, seed: u64, output: &str) -> Option<String> {
+    output.lines().rev().find_map(|line| {
+        let (key, value) = isolate::report_line(line)?;
+        match key {
+            "input" => Some(value.to_string()),
+            "case" => {
+                let input = task.random_input(&mut Rng::for_case(seed, value.parse().ok()?));
+                Some(T::call_source(task.function_name(), &input))
+            }
+            _ => None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_baseline::count_primes;
+    use crate::task::U64Function;
+
+    const SEED: u64 = 42;
+
+    fn count_of_primes() -> U64Function {
+        task::u64_tasks()
+            .into_iter()
+            .find(|task| task.name() == "count of primes")
+            .unwrap()
+    }
+
+    /// What a child prints, as report lines.
+    fn reported(lines: &[&str]) -> String {
+        lines
+            .iter()
+            .map(|line| format!("{}{}\n", isolate::REPORT_MARK, line))
+            .collect()
+    }
+
+    /// Wrong from 100 on.
+    fn wrong_from_100(n: u64) -> u64 {
+        if n >= 100 { 0 } else { count_primes(n) }
+    }
+
+    #[test]
+    fn splitmix_values_do_not_change() {
+        // the first output of SplitMix64 seeded with 0
+        assert_eq!(Rng::new(0).next_u64(), 0xE220_A839_7B1D_CDAF);
+        assert_eq!(Rng::for_case(SEED, 0).next_u64(), Rng::new(SEED).next_u64());
+    }
+
+    #[test]
+    fn cases_are_reproducible() {
+        let draw = |case| {
+            let mut rng = Rng::for_case(SEED, case);
+            [rng.next_u64(), rng.next_u64(), rng.below(1000)]
+        };
+        assert_eq!(draw(7), draw(7));
+        assert_ne!(draw(7), draw(8));
+
+        let task = count_of_primes();
+        let input = |case| task.random_input(&mut Rng::for_case(SEED, case));
+        assert!((0..100).all(|case| input(case) == input(case)));
+    }
+
+    #[test]
+    fn parse_output_of_a_candidate_that_passed() {
+        let output = reported(&["case 0", "case 1", "passed 2"]);
+        assert!(matches!(parse_output(&output), Some(Finding::Passed)));
+    }
+
+    #[test]
+    fn parse_output_of_a_disagreement() {
+        let output = reported(&[
+            "case 0",
+            "input count_primes(0)",
+            "input count_primes(5)",
+            "counterexample count_primes(5)",
+            "candidate 0",
+            "baseline 2",
+            "shrinks 1",
+        ]);
+        let Some(Finding::Disagrees {
+            input,
+            candidate,
+            baseline,
+            shrinks,
+        }) = parse_output(&output)
+        else {
+            panic!("not a disagreement");
+        };
+        assert_eq!(input, "count_primes(5)");
+        assert_eq!((candidate.as_str(), baseline.as_str()), ("0", "2"));
+        assert_eq!(shrinks, 1);
+    }
+
+    #[test]
+    fn parse_output_skips_what_the_candidate_prints() {
+        let output = format!(
+            "hello\n{}no newline before this {}",
+            reported(&["case 0"]),
+            reported(&["passed 1"])
+        );
+        assert!(matches!(parse_output(&output), Some(Finding::Passed)));
+    }
+
+    #[test]
+    fn parse_output_of_an_incomplete_report() {
+        let without_shrinks = reported(&[
+            "case 0",
+            "counterexample count_primes(5)",
+            "candidate 0",
+            "baseline 2",
+        ]);
+        assert!(parse_output(&without_shrinks).is_none());
+        assert!(parse_output(&reported(&["case 0", "shrinks many"])).is_none());
+        assert!(parse_output(&reported(&["case 0", "unknown 1"])).is_none());
+        // a child stopped before reporting anything
+        assert!(parse_output("").is_none());
+    }
+
+    #[test]
+    fn last_input_rebuilds_the_case_from_the_seed() {
+        let task = count_of_primes();
+        let case_1 = task.random_input(&mut Rng::for_case(SEED, 1));
+        assert_eq!(
+            last_input(&task, SEED, &reported(&["case 0", "case 1"])),
+            Some(format!("count_primes({})", case_1))
+        );
+        assert_eq!(
+            last_input(
+                &task,
+                SEED,
+                &(reported(&["case 0", "input count_primes(3)"]) + "output of the candidate\n")
+            ),
+            Some("count_primes(3)".to_string())
+        );
+        assert_eq!(last_input(&task, SEED, "started\n"), None);
+    }
+
+    #[test]
+    fn shrink_finds_the_simplest_disagreement() {
+        let task = count_of_primes();
+        let outputs = disagreement(&task, wrong_from_100, count_primes, &1000).unwrap();
+        assert_eq!(outputs, ("0".to_string(), "168".to_string()));
+
+        let shrunk = shrink(&task, wrong_from_100, count_primes, 1000, outputs, 1000);
+        assert_eq!(shrunk.input, 100);
+        assert_eq!(shrunk.outputs, ("0".to_string(), "25".to_string()));
+        assert!(shrunk.shrinks > 0 && shrunk.tried < 1000);
+    }
+
+    #[test]
+    fn shrink_stops_after_max_tries() {
+        let task = count_of_primes();
+        let outputs = disagreement(&task, wrong_from_100, count_primes, &1000).unwrap();
+        // 0 agrees, 500 disagrees, and nothing more is tried
+        let shrunk = shrink(&task, wrong_from_100, count_primes, 1000, outputs, 2);
+        assert_eq!(shrunk.input, 500);
+        assert_eq!((shrunk.shrinks, shrunk.tried), (1, 2));
+
+        let outputs = disagreement(&task, wrong_from_100, count_primes, &1000).unwrap();
+        let shrunk = shrink(&task, wrong_from_100, count_primes, 1000, outputs, 0);
+        assert_eq!(shrunk.input, 1000);
+        assert_eq!((shrunk.shrinks, shrunk.tried), (0, 0));
+    }
+}
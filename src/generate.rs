@@ -0,0 +1,355 @@
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::common::AICodeGenStatus;
+use crate::ingest::{self, Answer, Target};
+use crate::llm::{Client, Message, Role};
+use crate::task::Task;
+
+// The two-attempt protocol of the README, run by the harness: the baseline is
+// sent with the standard prompt, the answer is compiled in a scratch crate
+// together with a program that prints its results on the task's verification
+// inputs, and if it does not compile the compiler's output is sent back,
+// without explanation, for a second and last attempt.
+
+const ATTEMPTS: usize = 2;
+const CHECK_TIME_LIMIT: Duration = Duration::from_secs(10);
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+const PANIC_EXIT_CODE: i32 = 101;
+
+const BASELINE_SOURCE: &str = include_str!("module_baseline/mod.rs");
+
+pub struct Generated {
+    pub status: AICodeGenStatus,
+    /// The attempt the status was reached at.
+    pub attempt: u32,
+    /// The answer the status is about, None when no answer compiled.
+    pub answer: Option<Answer>,
+    /// The conversation, as Markdown.
+    pub transcript: String,
+}
+
+/// The baseline function of the task and the baseline helpers it calls, as
+/// pasted into the prompt.
+pub fn baseline_code(target: &Target) -> Result<String, String> {
+    let items = ingest::function_items(BASELINE_SOURCE);
+    let mut code = items
+        .iter()
+        .find(|(name, _)| *name == target.function_name)
+        .map(|(_, item)| item.clone())
+        .ok_or_else(|| format!("module_baseline has no function {}", target.function_name))?;
+
+    let mut included = vec![target.function_name.as_str()];
+    while let Some((name, item)) = items.iter().find(|(name, _)| {
+        !included.contains(&name.as_str()) && code.contains(&format!("{}(", name))
+    }) {
+        included.push(name);
+        code.push_str("\n\n");
+        code.push_str(item);
+    }
+    Ok(code)
+}
+
+pub fn generate<T: Task>(
+    client: &mut dyn Client,
+    task: &T,
+    baseline: T::Func,
+    target: &Target,
+    scratch: &Path,
+) -> Result<Generated, String> {
+    let prompt = format!(
+        "{}\n\n```rust\n{}\n```\n",
+        ingest::STANDARD_PROMPT,
+        baseline_code(target)?
+    );
+    let mut transcript = format!("## Prompt\n\n{}\n", prompt);
+    let mut messages = vec![Message::new(Role::User, prompt)];
+
+    let expected: Vec<String> = task
+        .verification_inputs()
+        .iter()
+        .map(|input| format!("{:?}", T::call(baseline, input)))
+        .collect();
+
+    for attempt in 1..=ATTEMPTS {
+        eprintln!("asking {} (attempt {})", client.model(), attempt);
+        let reply = client.complete(&messages)?;
+        transcript.push_str(&format!(
+            "## Answer {}\n\n{}\n\n",
+            attempt,
+            reply.trim_end()
+        ));
+
+        let answer = ingest::find_answer(&reply, target);
+        if attempt == 1 && answer.is_err() && !reply.contains("fn ") {
+            return Ok(Generated {
+                status: AICodeGenStatus::AIRefusedToAnswer,
+                attempt: 1,
+                answer: None,
+                transcript,
+            });
+        }
+
+        let diagnostics = match answer {
+            Err(e) => e,
+            Ok(answer) => match compile(scratch, task, target, &answer)? {
+                Err(diagnostics) => diagnostics,
+                Ok(binary) => {
+                    let mut status = check(&binary, &expected)?;
+                    if status == AICodeGenStatus::Ok && attempt > 1 {
+                        status = AICodeGenStatus::SecondTryOk;
+                    }
+                    transcript.push_str(&format!("## Result\n\n{}\n", status));
+                    return Ok(Generated {
+                        status,
+                        attempt: attempt as u32,
+                        answer: Some(answer),
+                        transcript,
+                    });
+                }
+            },
+        };
+
+        transcript.push_str(&format!(
+            "## Errors {}\n\n```text\n{}\n```\n\n",
+            attempt,
+            diagnostics.trim_end()
+        ));
+        messages.push(Message::new(Role::Assistant, reply));
+        messages.push(Message::new(Role::User, diagnostics));
+    }
+
+    let status = AICodeGenStatus::SecondTryCompileError;
+    transcript.push_str(&format!("## Result\n\n{}\n", status));
+    Ok(Generated {
+        status,
+        attempt: ATTEMPTS as u32,
+        answer: None,
+        transcript,
+    })
+}
+
+/// Builds the answer in the scratch crate, returning the check program or the
+/// compiler's diagnostics.
+fn compile<T: Task>(
+    scratch: &Path,
+    task: &T,
+    target: &Target,
+    answer: &Answer,
+) -> Result<Result<PathBuf, String>, String> {
+    let src = scratch.join("src");
+    fs::create_dir_all(&src).map_err(|e| format!("{}: {}", src.display(), e))?;
+
+    let function = format!("candidate::{}", answer.function_name());
+    let mut main = format!(
+        "mod candidate;\n\nconst _: fn{} = {};\n\nfn main() {{\n",
+        target.signature, function
+    );
+    for input in task.verification_inputs() {
+        main.push_str(&format!(
+            "    println!(\"{{:?}}\", {});\n",
+            T::call_source(&function, &input)
+        ));
+    }
+    main.push_str("}\n");
+
+    let mut manifest = String::from(
+        "[package]\nname = \"candidate\"\nversion = \"0.1.0\"\nedition = \"2024\"\n\n[workspace]\n",
+    );
+    // the one crate a candidate may use, as the grapheme task's baseline does
+    if answer.source().contains("unicode_segmentation") {
+        manifest.push_str("\n[dependencies]\nunicode-segmentation = \"1\"\n");
+    }
+    write(&scratch.join("Cargo.toml"), &manifest)?;
+    write(&src.join("main.rs"), &main)?;
+    write(
+        &src.join("candidate.rs"),
+        &format!(
+            "#![allow(dead_code, unused, clippy::all)]\n\n{}",
+            answer.source()
+        ),
+    )?;
+
+    let output = Command::new("cargo")
+        .args(["build", "--release", "--quiet", "--color", "never"])
+        .current_dir(scratch)
+        .output()
+        .map_err(|e| format!("cannot run cargo: {}", e))?;
+    if !output.status.success() {
+        return Ok(Err(String::from_utf8_lossy(&output.stderr).to_string()));
+    }
+
+    Ok(Ok(scratch.join("target").join("release").join(format!(
+        "candidate{}",
+        std::env::consts::EXE_SUFFIX
+    ))))
+}
+
+/// Runs the check program and compares what it prints with the baseline.
+fn check(binary: &Path, expected: &[String]) -> Result<AICodeGenStatus, String> {
+    let mut child = Command::new(binary)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("{}: {}", binary.display(), e))?;
+
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(|e| e.to_string())? {
+            break status;
+        }
+        if started.elapsed() > CHECK_TIME_LIMIT {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(AICodeGenStatus::Timeout);
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    let mut stdout = String::new();
+    if let Some(mut pipe) = child.stdout.take() {
+        let _ = pipe.read_to_string(&mut stdout);
+    }
+
+    Ok(match status.code() {
+        Some(0) if stdout.lines().eq(expected.iter().map(String::as_str)) => AICodeGenStatus::Ok,
+        Some(0) => AICodeGenStatus::IncorrectResult,
+        Some(PANIC_EXIT_CODE) => AICodeGenStatus::Panicked,
+        _ => AICodeGenStatus::Crashed,
+    })
+}
+
+/// Saves the conversation under `dir`, next to the other answers of the engine.
+pub fn save_transcript(
+    dir: &Path,
+    module: &str,
+    target: &Target,
+    date: chrono::NaiveDate,
+    transcript: &str,
+) -> Result<PathBuf, String> {
+    let dir = dir.join(module);
+    fs::create_dir_all(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+
+    let stem = format!("{}_{}", target.function_name, date.format("%Y%m%d"));
+    let mut path = dir.join(format!("{}.md", stem));
+    for n in 2.. {
+        if !path.exists() {
+            break;
+        }
+        path = dir.join(format!("{}_{}.md", stem, n));
+    }
+    write(&path, transcript)?;
+    Ok(path)
+}
+
+fn write(path: &Path, contents: &str) -> Result<(), String> {
+    fs::write(path, contents).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::FixtureClient;
+    use crate::task::{self, U64Function};
+
+    const CORRECT: &str = "```rust\nfn count_primes(n: u64) -> u64 {\n    (2..n).filter(|&x| (2..x).all(|d| x % d != 0)).count() as u64\n}\n```\n";
+    const WRONG: &str = "```rust\nfn count_primes(n: u64) -> u64 {\n    n / 2\n}\n```\n";
+    const NOT_COMPILING: &str =
+        "```rust\nfn count_primes(n: u64) -> u64 {\n    n.count_primes_fast()\n}\n```\n";
+
+    fn count_primes() -> U64Function {
+        task::u64_tasks()
+            .into_iter()
+            .find(|task| task.name() == "count of primes")
+            .unwrap()
+    }
+
+    /// Generates with the answers replayed in order, in a scratch directory of
+    /// the test's own under target/ so that the builds are cached between runs.
+    fn generate_with(test: &str, answers: &[&str]) -> Generated {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("target")
+            .join("test-generate")
+            .join(test);
+        fs::create_dir_all(&dir).unwrap();
+        let fixtures: Vec<PathBuf> = answers
+            .iter()
+            .enumerate()
+            .map(|(i, answer)| {
+                let path = dir.join(format!("answer{}.md", i + 1));
+                fs::write(&path, answer).unwrap();
+                path
+            })
+            .collect();
+
+        let task = count_primes();
+        let mut client = FixtureClient::new("fixture", fixtures);
+        generate(
+            &mut client,
+            &task,
+            crate::module_baseline::count_primes,
+            &Target::new(&task),
+            &dir.join("scratch"),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn baseline_code_includes_the_helpers_called() {
+        let code = baseline_code(&Target::new(&count_primes())).unwrap();
+        assert!(code.starts_with("/// "));
+        assert!(code.contains("pub fn count_primes(n: u64) -> u64 {"));
+        assert!(code.contains("fn is_prime(num: u64) -> bool {"));
+        assert!(!code.contains("fn fibonacci"));
+    }
+
+    #[test]
+    fn baseline_code_of_an_unknown_function_fails() {
+        let mut target = Target::new(&count_primes());
+        target.function_name = "count_twin_primes".to_string();
+        assert_eq!(
+            baseline_code(&target).err().unwrap(),
+            "module_baseline has no function count_twin_primes"
+        );
+    }
+
+    #[test]
+    fn an_answer_without_code_is_a_refusal() {
+        let generated = generate_with("refused", &["I cannot help with optimizing this function."]);
+        assert_eq!(generated.status, AICodeGenStatus::AIRefusedToAnswer);
+        assert_eq!(generated.attempt, 1);
+        assert!(generated.answer.is_none());
+    }
+
+    #[test]
+    fn an_answer_fixed_after_the_errors_is_second_try_ok() {
+        let generated = generate_with("second-try-ok", &[NOT_COMPILING, CORRECT]);
+        assert_eq!(generated.status, AICodeGenStatus::SecondTryOk);
+        assert_eq!(generated.attempt, 2);
+        assert_eq!(generated.answer.unwrap().function_name(), "count_primes");
+        assert!(generated.transcript.contains("## Errors 1\n\n```text\n"));
+        assert!(generated.transcript.contains("count_primes_fast"));
+        assert!(generated.transcript.ends_with("## Result\n\nSecondTryOk\n"));
+    }
+
+    #[test]
+    fn two_answers_that_do_not_compile_are_second_try_compile_error() {
+        let generated = generate_with("second-try-compile-error", &[NOT_COMPILING, NOT_COMPILING]);
+        assert_eq!(generated.status, AICodeGenStatus::SecondTryCompileError);
+        assert_eq!(generated.attempt, 2);
+        assert!(generated.answer.is_none());
+        assert!(generated.transcript.contains("## Errors 2\n"));
+    }
+
+    #[test]
+    fn an_answer_with_other_results_is_incorrect() {
+        let generated = generate_with("incorrect-result", &[WRONG]);
+        assert_eq!(generated.status, AICodeGenStatus::IncorrectResult);
+        assert_eq!(generated.attempt, 1);
+        assert!(generated.answer.is_some());
+    }
+}
@@ -0,0 +1,113 @@
+use serde::{Deserialize, Serialize};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
+
+// Much of a candidate's speedup can come from allocating less: the baseline
+// Levenshtein allocates a row per character, a sieve allocates n bytes. The
+// harness' global allocator is the system one with counters that only count
+// while enabled, so timing pays one relaxed load per allocation. With
+// --allocations the child runs the candidate once more after timing, with
+// counting enabled, and reports allocations and bytes per call and the peak
+// of the heap above what was live before. A realloc counts as an allocation
+// of its new size.
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+static COUNTING: AtomicBool = AtomicBool::new(false);
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static BYTES: AtomicU64 = AtomicU64::new(0);
+/// Bytes allocated minus bytes freed since counting started, below zero when
+/// memory allocated before is freed.
+static LIVE: AtomicI64 = AtomicI64::new(0);
+static PEAK: AtomicI64 = AtomicI64::new(0);
+
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        record_alloc(layout.size());
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        record_alloc(layout.size());
+        unsafe { System.alloc_zeroed(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        record_free(layout.size());
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        record_free(layout.size());
+        record_alloc(new_size);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+fn record_alloc(size: usize) {
+    if COUNTING.load(Ordering::Relaxed) {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        BYTES.fetch_add(size as u64, Ordering::Relaxed);
+        let live = LIVE.fetch_add(size as i64, Ordering::Relaxed) + size as i64;
+        PEAK.fetch_max(live, Ordering::Relaxed);
+    }
+}
+
+fn record_free(size: usize) {
+    if COUNTING.load(Ordering::Relaxed) {
+        LIVE.fetch_sub(size as i64, Ordering::Relaxed);
+    }
+}
+
+/// What a candidate allocated, averaged over the counted calls.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Allocations {
+    pub per_call: f64,
+    pub bytes_per_call: f64,
+    /// Highest the heap got above where it was before the first call.
+    pub peak_bytes: u64,
+}
+
+impl Allocations {
+    /// The fields separated by spaces, for the child's output.
+    pub fn fields(self) -> String {
+        format!(
+            "{} {} {}",
+            self.per_call, self.bytes_per_call, self.peak_bytes
+        )
+    }
+
+    pub fn parse(fields: &str) -> Option<Allocations> {
+        let mut fields = fields.split(' ');
+        let allocations = Allocations {
+            per_call: fields.next()?.parse().ok()?,
+            bytes_per_call: fields.next()?.parse().ok()?,
+            peak_bytes: fields.next()?.parse().ok()?,
+        };
+        fields.next().is_none().then_some(allocations)
+    }
+}
+
+/// Runs `f` `calls` times, counting what it allocates.
+pub fn count<F: FnMut()>(mut f: F, calls: u64) -> Allocations {
+    for counter in [&ALLOCATIONS, &BYTES] {
+        counter.store(0, Ordering::Relaxed);
+    }
+    for counter in [&LIVE, &PEAK] {
+        counter.store(0, Ordering::Relaxed);
+    }
+    COUNTING.store(true, Ordering::SeqCst);
+    for _ in 0..calls {
+        f();
+    }
+    COUNTING.store(false, Ordering::SeqCst);
+
+    Allocations {
+        per_call: ALLOCATIONS.load(Ordering::Relaxed) as f64 / calls as f64,
+        bytes_per_call: BYTES.load(Ordering::Relaxed) as f64 / calls as f64,
+        peak_bytes: PEAK.load(Ordering::Relaxed).max(0) as u64,
+    }
+}
@@ -0,0 +1,270 @@
+use prettytable::{Cell, Row, Table};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use crate::cli::{Filter, Format};
+use crate::common;
+use crate::environment::Environment;
+use crate::export::{self, Host, Record, Report};
+use crate::fingerprint::Build;
+use crate::stats;
+
+// Every run appends its records to a JSON-lines file, one line per candidate,
+// and nothing ever rewrites it. A run is the block of consecutive lines that
+// share a run timestamp and host; `compare` matches the records of two runs by
+// engine, function and candidate date.
+
+/// One line of the history file.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Entry {
+    pub run_timestamp: String,
+    pub git_revision: String,
+    pub host: Host,
+    #[serde(default)]
+    pub build: Build,
+    pub duration_sec: u64,
+    pub input_size: Option<u64>,
+    #[serde(default = "export::default_input_pool")]
+    pub input_pool: usize,
+    #[serde(default = "export::default_timer")]
+    pub timer: String,
+    #[serde(default)]
+    pub environment: Environment,
+    #[serde(flatten)]
+    pub record: Record,
+}
+
+/// The records of one run, as read back from the history file.
+pub struct Run {
+    pub run_timestamp: String,
+    pub git_revision: String,
+    pub host: Host,
+    pub build: Build,
+    pub duration_sec: u64,
+    pub input_size: Option<u64>,
+    pub input_pool: usize,
+    pub timer: String,
+    pub environment: Environment,
+    pub records: Vec<Record>,
+}
+
+pub fn append(path: &Path, report: &Report) -> Result<(), String> {
+    let error = |e: std::io::Error| format!("{}: {}", path.display(), e);
+
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(error)?;
+    }
+
+    let mut lines = String::new();
+    for record in &report.results {
+        let entry = Entry {
+            run_timestamp: report.run_timestamp.clone(),
+            git_revision: report.git_revision.clone(),
+            host: report.host.clone(),
+            build: report.build.clone(),
+            duration_sec: report.duration_sec,
+            input_size: report.input_size,
+            input_pool: report.input_pool,
+            timer: report.timer.clone(),
+            environment: report.environment.clone(),
+            record: record.clone(),
+        };
+        lines.push_str(&serde_json::to_string(&entry).map_err(|e| e.to_string())?);
+        lines.push('\n');
+    }
+
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(lines.as_bytes()))
+        .map_err(error)
+}
+
+/// Reads every run in the history file, oldest first.
+pub fn load(path: &Path) -> Result<Vec<Run>, String> {
+    let history = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut runs: Vec<Run> = Vec::new();
+
+    for (n, line) in history.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: Entry = serde_json::from_str(line)
+            .map_err(|e| format!("{}:{}: {}", path.display(), n + 1, e))?;
+
+        match runs.last_mut() {
+            Some(run)
+                if run.run_timestamp == entry.run_timestamp
+                    && run.host.hostname == entry.host.hostname =>
+            {
+                run.records.push(entry.record)
+            }
+            _ => runs.push(Run {
+                run_timestamp: entry.run_timestamp,
+                git_revision: entry.git_revision,
+                host: entry.host,
+                build: entry.build,
+                duration_sec: entry.duration_sec,
+                input_size: entry.input_size,
+                input_pool: entry.input_pool,
+                timer: entry.timer,
+                environment: entry.environment,
+                records: vec![entry.record],
+            }),
+        }
+    }
+
+    Ok(runs)
+}
+
+/// Finds a run by its number in `print_runs` or by (a prefix of) its timestamp.
+/// A number beyond the runs, such as the year "2026", is taken as a timestamp.
+pub fn find_run<'a>(runs: &'a [Run], id: &str) -> Result<&'a Run, String> {
+    let number = id.parse::<usize>().ok();
+    if let Some(run) = number.and_then(|number| runs.get(number.wrapping_sub(1))) {
+        return Ok(run);
+    }
+
+    let mut matching = runs.iter().filter(|run| run.run_timestamp.starts_with(id));
+    match (matching.next(), matching.next(), number) {
+        (Some(run), None, _) => Ok(run),
+        (None, _, Some(number)) => Err(format!(
+            "there is no run {} and no run with that timestamp, the history has {}",
+            number,
+            runs.len()
+        )),
+        (None, _, None) => Err(format!("no run with timestamp '{}'", id)),
+        (Some(_), Some(_), _) => Err(format!("more than one run with timestamp '{}'", id)),
+    }
+}
+
+pub fn print_runs(runs: &[Run]) {
+    let mut table = Table::new();
+    table.set_format(common::table_format(Format::Table));
+    table.set_titles(Row::new(vec![
+        Cell::new("Run"),
+        Cell::new("Timestamp"),
+        Cell::new("Host"),
+        Cell::new("Revision"),
+        Cell::new("Duration"),
+        Cell::new("Input Size"),
+        Cell::new("Records"),
+    ]));
+
+    for (i, run) in runs.iter().enumerate() {
+        table.add_row(Row::new(vec![
+            Cell::new(&(i + 1).to_string()),
+            Cell::new(&run.run_timestamp),
+            Cell::new(&run.host.hostname),
+            Cell::new(&run.git_revision),
+            Cell::new(&format!("{} s", run.duration_sec)),
+            Cell::new(
+                &run.input_size
+                    .map_or("-".to_string(), |size| size.to_string()),
+            ),
+            Cell::new(&run.records.len().to_string()),
+        ]));
+    }
+
+    table.printstd();
+}
+
+/// Prints every candidate present in both runs with its change in throughput.
+/// Changes that pass Welch's t-test at 95% are highlighted as improvements
+/// (green) or regressions (red).
+pub fn print_comparison(old: &Run, new: &Run, filter: &Filter) {
+    println!(
+        "Comparing run {} ({}, {}) with run {} ({}, {})",
+        old.run_timestamp,
+        old.host.hostname,
+        old.git_revision,
+        new.run_timestamp,
+        new.host.hostname,
+        new.git_revision
+    );
+    if old.host.hostname != new.host.hostname {
+        eprintln!("warning: the runs are from different hosts, so timings are not comparable");
+    }
+    if old.build != new.build {
+        eprintln!(
+            "warning: the runs were built differently ({} / {})",
+            old.build.describe(),
+            new.build.describe()
+        );
+    }
+    if old.duration_sec != new.duration_sec
+        || old.input_size != new.input_size
+        || old.input_pool != new.input_pool
+        || old.timer != new.timer
+    {
+        eprintln!("warning: the runs used different durations, input sizes, input pools or timers");
+    }
+    if old.environment.pinned_cpu != new.environment.pinned_cpu {
+        eprintln!("warning: one run was pinned to a CPU and the other was not, or to another one");
+    }
+
+    let mut table = Table::new();
+    table.set_format(common::table_format(Format::Table));
+    table.set_titles(Row::new(vec![
+        Cell::new("AI Engine"),
+        Cell::new("Function"),
+        Cell::new("Date"),
+        Cell::new("Old Status"),
+        Cell::new("New Status"),
+        Cell::new("Old Iter/Sec"),
+        Cell::new("New Iter/Sec"),
+        Cell::new("Change"),
+        Cell::new("Verdict"),
+    ]));
+
+    for new_record in &new.records {
+        if !filter.engine_selected(&new_record.engine)
+            || !filter.task_selected(&new_record.function)
+        {
+            continue;
+        }
+        let Some(old_record) = old.records.iter().find(|old_record| {
+            old_record.engine == new_record.engine
+                && old_record.function == new_record.function
+                && old_record.date == new_record.date
+        }) else {
+            continue;
+        };
+
+        let (old_summary, new_summary) = (&old_record.samples, &new_record.samples);
+        let change = if old_summary.median > 0.0 {
+            format!(
+                "{:+.1}%",
+                (new_summary.median / old_summary.median - 1.0) * 100.0
+            )
+        } else {
+            "-".to_string()
+        };
+        let verdict = if old_record.status != new_record.status {
+            Cell::new("status changed").style_spec("Fy")
+        } else if !stats::differs_significantly(old_summary, new_summary) {
+            Cell::new("no significant change")
+        } else if new_summary.median > old_summary.median {
+            Cell::new("improved").style_spec("Fg")
+        } else {
+            Cell::new("regressed").style_spec("Fr")
+        };
+
+        table.add_row(Row::new(vec![
+            Cell::new(&new_record.engine),
+            Cell::new(&new_record.function),
+            Cell::new(&new_record.date),
+            Cell::new(&old_record.status),
+            Cell::new(&new_record.status),
+            Cell::new(&format!("{:.2}", old_summary.median)),
+            Cell::new(&format!("{:.2}", new_summary.median)),
+            Cell::new(&change),
+            verdict,
+        ]));
+    }
+
+    table.printstd();
+}
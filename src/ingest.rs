@@ -0,0 +1,1148 @@
+use chrono::{Datelike, NaiveDate};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::cli::IngestArgs;
+use crate::registry::Registered;
+
+// `ingest` turns a saved AI response into a candidate without hand editing:
+// the Rust code block that defines a function with the task's signature is
+// written, unchanged apart from making that function public and not inlined,
+// to a file of its own under src/module_<engine>/ (so helper functions never
+// collide with earlier attempts), and a `candidate!` for it is added to the
+// engine's get_candidates vector, creating the module (and its `mod` line in
+// main.rs) for a new engine.
+
+/// The prompt in the README, recorded when `--prompt` is not given.
+pub const STANDARD_PROMPT: &str = "You are a highly skilled Rust developer. Your task is to optimize the following Rust function for maximum speed and efficiency. The code will run on a x86-x64 system with an AMD Ryzen 7 processor. Readability of the code is not important. Please provide the optimized code. Here is the function that needs optimization:";
+
+/// Put on the `mod` line of every candidate module: the code is pasted
+/// verbatim, so lints newer than it are not applied to it.
+const CANDIDATE_MOD_ATTRIBUTE: &str = "#[allow(clippy::manual_is_multiple_of)]";
+
+/// Where a candidate of one task goes.
+pub struct Target {
+    pub task_name: String,
+    pub function_name: String,
+    /// Parameter and return types, see `Task::SIGNATURE`.
+    pub signature: &'static str,
+    /// Name of the `Task` implementation, e.g. StringDistance.
+    pub task_type: &'static str,
+    /// Module function returning the candidates of the family, e.g. get_candidates2.
+    pub getter: &'static str,
+}
+
+impl Target {
+    pub fn new<T: Registered>(task: &T) -> Self {
+        Target {
+            task_name: task.name().to_string(),
+            function_name: task.function_name().to_string(),
+            signature: T::SIGNATURE,
+            task_type: std::any::type_name::<T>()
+                .rsplit("::")
+                .next()
+                .unwrap_or_default(),
+            getter: T::GETTER,
+        }
+    }
+}
+
+/// A top-level function found in a code block.
+struct Function {
+    name: String,
+    signature: String,
+    /// Byte offsets of the line the function starts on and of its `fn`.
+    line_start: usize,
+    fn_start: usize,
+}
+
+/// The function a response offers for a task, in the code block defining it.
+pub struct Answer {
+    code: String,
+    function: Function,
+}
+
+impl Answer {
+    pub fn function_name(&self) -> &str {
+        &self.function.name
+    }
+
+    /// The code block with the candidate function made public and not
+    /// inlined, and nothing else changed.
+    pub fn source(&self) -> String {
+        let (code, function) = (&self.code, &self.function);
+
+        // keep the attributes directly above the function, except inline ones
+        let mut attributes_start = function.line_start;
+        while attributes_start > 0 {
+            let previous = code[..attributes_start - 1]
+                .rfind('\n')
+                .map_or(0, |i| i + 1);
+            if !code[previous..attributes_start]
+                .trim_start()
+                .starts_with("#[")
+            {
+                break;
+            }
+            attributes_start = previous;
+        }
+        let attributes: String = code[attributes_start..function.line_start]
+            .lines()
+            .filter(|line| !line.contains("inline"))
+            .map(|line| format!("{}\n", line))
+            .collect();
+
+        format!(
+            "{}{}#[inline(never)]\npub {}{}",
+            &code[..attributes_start],
+            attributes,
+            &code[function.line_start..function.fn_start]
+                .split_whitespace()
+                .filter(|word| !word.starts_with("pub"))
+                .map(|word| format!("{} ", word))
+                .collect::<String>(),
+            &code[function.fn_start..]
+        )
+    }
+}
+
+/// Adds the candidate in the response `args.response` and returns the files written.
+pub fn ingest(args: &IngestArgs, target: &Target) -> Result<Vec<PathBuf>, String> {
+    let response = read(&args.response)?;
+    let answer = find_answer(&response, target)
+        .map_err(|e| format!("{}: {}", args.response.display(), e))?;
+    add_candidate(args, target, Some(&answer))
+}
+
+/// Finds the function a response offers for the task.
+pub fn find_answer(response: &str, target: &Target) -> Result<Answer, String> {
+    let blocks = code_blocks(response);
+    if blocks.is_empty() {
+        return Err("no Rust code blocks found".to_string());
+    }
+    let (code, function) = find_candidate(&blocks, target)?;
+    Ok(Answer { code, function })
+}
+
+/// Writes the candidate to its own file and registers it, returning the files
+/// written. Without an answer the candidate is a stub, for statuses that have
+/// no code.
+pub fn add_candidate(
+    args: &IngestArgs,
+    target: &Target,
+    answer: Option<&Answer>,
+) -> Result<Vec<PathBuf>, String> {
+    let date = args
+        .date
+        .unwrap_or_else(|| chrono::Local::now().date_naive());
+
+    let (module, module_exists) = find_module(&args.src, &args.engine, args.module.as_deref())?;
+    let module_dir = args.src.join(format!("module_{}", module));
+    let mod_rs = module_dir.join("mod.rs");
+
+    let mut file_stem = format!(
+        "{}_{:04}{:02}{:02}",
+        target.function_name,
+        date.year(),
+        date.month(),
+        date.day()
+    );
+    for n in 2.. {
+        if !module_dir.join(format!("{}.rs", file_stem)).exists() {
+            break;
+        }
+        file_stem = format!(
+            "{}_{:04}{:02}{:02}_{}",
+            target.function_name,
+            date.year(),
+            date.month(),
+            date.day(),
+            n
+        );
+    }
+    let candidate_rs = module_dir.join(format!("{}.rs", file_stem));
+
+    let item = candidate_item(args, target, answer, &file_stem, date);
+
+    // Everything is worked out before anything is written, so that a
+    // failure leaves the tree as it was.
+    let getter_header = format!(
+        "pub fn {}() -> CandidateInfo<{}>",
+        target.getter, target.task_type
+    );
+    let mod_source = if module_exists {
+        let source = read(&mod_rs)?;
+        let source = match source.find(&getter_header) {
+            Some(at) => insert_vec_item(&source, at, &item)?,
+            None => add_getter(&source, &getter(&args.engine, target, &item), target)
+                .map_err(|e| format!("{}: {}", mod_rs.display(), e))?,
+        };
+        // the first submodule gets a blank line between it and the imports
+        let declaration = if source.lines().any(|line| line.starts_with("mod ")) {
+            format!("mod {};", file_stem)
+        } else {
+            format!("\nmod {};", file_stem)
+        };
+        add_line_after_last(&source, &["use ", "mod "], &declaration)
+    } else {
+        new_module(&args.engine, target, &file_stem, &item)
+    };
+
+    let main_rs = args.src.join("main.rs");
+    let main_source = if module_exists {
+        None
+    } else {
+        Some(
+            add_mod_declaration(&read(&main_rs)?, &format!("module_{}", module))
+                .map_err(|e| format!("{}: {}", main_rs.display(), e))?,
+        )
+    };
+
+    fs::create_dir_all(&module_dir).map_err(|e| format!("{}: {}", module_dir.display(), e))?;
+    write(&candidate_rs, &candidate_source(args, target, answer, date))?;
+    write(&mod_rs, &mod_source)?;
+    let mut changed = vec![candidate_rs, mod_rs];
+    if let Some(main) = main_source {
+        write(&main_rs, &main)?;
+        changed.push(main_rs);
+    }
+
+    Ok(changed)
+}
+
+/// The contents of the fenced code blocks tagged rust or rs, or untagged.
+fn code_blocks(text: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut lines = text.lines();
+
+    while let Some(line) = lines.next() {
+        let line = line.trim_start();
+        let Some(fence_char) = line.chars().next().filter(|c| *c == '`' || *c == '~') else {
+            continue;
+        };
+        let fence_len = line.len() - line.trim_start_matches(fence_char).len();
+        if fence_len < 3 {
+            continue;
+        }
+        let language = line[fence_len..].trim().to_lowercase();
+
+        let mut block = String::new();
+        for line in lines.by_ref() {
+            let closing = line.trim();
+            if closing.len() >= fence_len && closing.chars().all(|c| c == fence_char) {
+                break;
+            }
+            block.push_str(line);
+            block.push('\n');
+        }
+        if language.is_empty() || language == "rust" || language == "rs" {
+            blocks.push(block);
+        }
+    }
+
+    blocks
+}
+
+/// Picks the function the response offers for the task: one with the task's
+/// signature, preferably with the baseline's name. When several blocks have
+/// one, the last wins, as answers tend to end with their final version.
+fn find_candidate(blocks: &[String], target: &Target) -> Result<(String, Function), String> {
+    let wanted = normalize_signature(target.signature);
+    let mut matching: Vec<(&String, Function)> = blocks
+        .iter()
+        .flat_map(|block| functions(block).into_iter().map(move |f| (block, f)))
+        .filter(|(_, function)| function.signature == wanted)
+        .collect();
+
+    let index = match matching
+        .iter()
+        .rposition(|(_, function)| function.name == target.function_name)
+    {
+        Some(i) => i,
+        None => {
+            let mut names: Vec<&str> = matching.iter().map(|(_, f)| f.name.as_str()).collect();
+            names.sort_unstable();
+            names.dedup();
+            match names.len() {
+                0 => {
+                    return Err(format!(
+                        "no function with the signature {} in the Rust code blocks",
+                        target.signature
+                    ));
+                }
+                1 => matching.len() - 1,
+                _ => {
+                    return Err(format!(
+                        "more than one function with the signature {}: {}",
+                        target.signature,
+                        names.join(", ")
+                    ));
+                }
+            }
+        }
+    };
+
+    let (block, function) = matching.swap_remove(index);
+    Ok((block.clone(), function))
+}
+
+/// The free functions of `code`: those whose `fn` line is not indented.
+fn functions(code: &str) -> Vec<Function> {
+    let mut functions = Vec::new();
+    let mut line_start = 0;
+
+    for line in code.split_inclusive('\n') {
+        if let Some(fn_pos) = line.find("fn ") {
+            let qualifiers = &line[..fn_pos];
+            let plain = !line.starts_with(char::is_whitespace)
+                && qualifiers
+                    .split_whitespace()
+                    .all(|word| matches!(word, "pub" | "pub(crate)" | "pub(super)" | "const"));
+            if plain && let Some((name, signature)) = parse_header(&code[line_start + fn_pos + 3..])
+            {
+                functions.push(Function {
+                    name,
+                    signature,
+                    line_start,
+                    fn_start: line_start + fn_pos,
+                });
+            }
+        }
+        line_start += line.len();
+    }
+
+    functions
+}
+
+/// The free functions of `code` by name, each with the doc comments above it
+/// but without its attributes or other comments.
+pub fn function_items(code: &str) -> Vec<(String, String)> {
+    let mut items = Vec::new();
+    for function in functions(code) {
+        let Some(end) = code[function.fn_start..].find('{').and_then(|open| {
+            let open = function.fn_start + open;
+            Some(open + matching_close(&code[open..], '{', '}')?)
+        }) else {
+            continue;
+        };
+
+        let mut docs = Vec::new();
+        for line in code[..function.line_start].lines().rev() {
+            let line = line.trim();
+            if line.starts_with("///") {
+                docs.push(line);
+            } else if !line.starts_with("#[") && !line.starts_with("//") {
+                break;
+            }
+        }
+        docs.reverse();
+
+        let mut item: String = docs.iter().map(|line| format!("{}\n", line)).collect();
+        item.push_str(&code[function.line_start..=end]);
+        items.push((function.name, item));
+    }
+    items
+}
+
+/// Parses `name<generics>(params) -> type`, returning the name and the
+/// normalized signature.
+fn parse_header(header: &str) -> Option<(String, String)> {
+    let header = header.trim_start();
+    let name_len = header
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(header.len());
+    if name_len == 0 {
+        return None;
+    }
+    let mut rest = header[name_len..].trim_start();
+    if rest.starts_with('<') {
+        rest = rest[matching_close(rest, '<', '>')? + 1..].trim_start();
+    }
+    rest.starts_with('(')
+        .then(|| (header[..name_len].to_string(), normalize_signature(rest)))
+}
+
+/// Reduces `(name: type, ...) -> type` to `(type,...)->type` without
+/// whitespace or lifetimes, so that signatures can be compared as text.
+fn normalize_signature(signature: &str) -> String {
+    let Some(close) = matching_close(signature, '(', ')') else {
+        return String::new();
+    };
+    let types: Vec<String> = split_top_level(&signature[1..close])
+        .into_iter()
+        .map(|param| {
+            // the type follows the first single colon; `self` has none
+            let colon = param
+                .char_indices()
+                .find(|&(i, c)| {
+                    c == ':' && !param[i + 1..].starts_with(':') && !param[..i].ends_with(':')
+                })
+                .map(|(i, _)| i + 1);
+            strip_type(&param[colon.unwrap_or(0)..])
+        })
+        .collect();
+
+    let rest = &signature[close + 1..];
+    let end = rest.find(['{', ';']).unwrap_or(rest.len());
+    let rest = rest[..end]
+        .split(" where")
+        .next()
+        .unwrap_or_default()
+        .trim();
+    let output = rest.strip_prefix("->").map_or("()".to_string(), strip_type);
+
+    format!("({})->{}", types.join(","), output)
+}
+
+fn strip_type(ty: &str) -> String {
+    let mut stripped = String::new();
+    let mut chars = ty.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\'' {
+            while chars
+                .next_if(|c| c.is_alphanumeric() || *c == '_')
+                .is_some()
+            {}
+        } else if !c.is_whitespace() {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+/// Index of the bracket closing the one `text` starts with.
+fn matching_close(text: &str, open: char, close: char) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        if c == open {
+            depth += 1;
+        } else if c == close {
+            depth -= 1;
+            if depth == 0 {
+                return Some(i);
+            }
+        }
+    }
+    None
+}
+
+/// Splits on the commas that are not nested in brackets, dropping empty parts.
+fn split_top_level(text: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' | '[' | '<' | '{' => depth += 1,
+            ')' | ']' | '>' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(text[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(text[start..].trim());
+    parts.retain(|part| !part.is_empty());
+    parts
+}
+
+/// The module directory name (without `module_`) the candidates of the engine go in.
+pub fn module_name(src: &Path, engine: &str, module: Option<&str>) -> Result<String, String> {
+    find_module(src, engine, module).map(|(name, _)| name)
+}
+
+/// The module directory name (without `module_`) for the engine, and whether
+/// it already exists. An engine that already has candidates keeps its module.
+fn find_module(src: &Path, engine: &str, module: Option<&str>) -> Result<(String, bool), String> {
+    let engine_literal = format!("{:?},", engine);
+    let entries = fs::read_dir(src).map_err(|e| format!("{}: {}", src.display(), e))?;
+    for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let Some(name) = file_name.strip_prefix("module_") else {
+            continue;
+        };
+        let owns_engine = fs::read_to_string(entry.path().join("mod.rs")).is_ok_and(|source| {
+            source
+                .split("CandidateInfo::new(")
+                .skip(1)
+                .any(|rest| rest.trim_start().starts_with(&engine_literal))
+        });
+        if owns_engine {
+            if let Some(module) = module.filter(|module| *module != name) {
+                return Err(format!(
+                    "the candidates of {} are in module_{}, not module_{}",
+                    engine, name, module
+                ));
+            }
+            return Ok((name.to_string(), true));
+        }
+    }
+
+    let name = match module {
+        Some(module) => module.to_string(),
+        None => engine
+            .to_lowercase()
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>()
+            .join("_"),
+    };
+    if !name.starts_with(|c: char| c.is_ascii_lowercase())
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+    {
+        return Err(format!(
+            "'{}' is not a usable module name, choose one with --module",
+            name
+        ));
+    }
+    let exists = src.join(format!("module_{}", name)).join("mod.rs").exists();
+    Ok((name, exists))
+}
+
+/// Adds `item` at the end of the first `vec![...]` after `at`.
+fn insert_vec_item(source: &str, at: usize, item: &str) -> Result<String, String> {
+    let rest = &source[at..];
+    let open = rest.find("vec![").ok_or("expected a vec![...] list")? + "vec!".len();
+    let close =
+        open + matching_close(&rest[open..], '[', ']').ok_or("unterminated vec![...] list")?;
+    let line_indent = indent_at(rest, open);
+    let mut updated = source[..at + open + 1].to_string();
+
+    let inner = &rest[open + 1..close];
+    // one element per line as rustfmt leaves them, or rewritten that way
+    if inner.trim_start_matches(' ').starts_with('\n') {
+        let item_indent = inner
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .map_or(format!("{}    ", line_indent), |line| {
+                line[..line.len() - line.trim_start().len()].to_string()
+            });
+        let inner = inner.trim_end();
+        updated.push_str(inner);
+        if !inner.ends_with(',') {
+            updated.push(',');
+        }
+        updated.push_str(&format!(
+            "\n{}{},\n{}",
+            item_indent,
+            item.replace('\n', &format!("\n{}", item_indent)),
+            indent_at(rest, close)
+        ));
+    } else {
+        // the existing elements keep their layout, one level deeper
+        let item_indent = format!("{}    ", line_indent);
+        for element in split_top_level(inner) {
+            updated.push_str(&format!(
+                "\n{}{},",
+                item_indent,
+                element.replace('\n', "\n    ")
+            ));
+        }
+        updated.push_str(&format!(
+            "\n{}{},\n{}",
+            item_indent,
+            item.replace('\n', &format!("\n{}", item_indent)),
+            line_indent
+        ));
+    }
+
+    updated.push_str(&rest[close..]);
+    Ok(updated)
+}
+
+/// The `candidate!` for the answer, laid out as rustfmt would in a getter's
+/// vector; continuation lines are indented relative to its first line.
+fn candidate_item(
+    args: &IngestArgs,
+    target: &Target,
+    answer: Option<&Answer>,
+    file_stem: &str,
+    date: NaiveDate,
+) -> String {
+    let arguments = [
+        target.task_type.to_string(),
+        format!("{:?}", target.task_name),
+        format!(
+            "{}::{}",
+            file_stem,
+            answer.map_or(target.function_name.as_str(), Answer::function_name)
+        ),
+        format!("\"{}\"", date.format("%Y-%m-%d")),
+        args.status.to_string(),
+    ];
+    let mut calls = Vec::new();
+    if let Some(model_version) = &args.model_version {
+        calls.push(format!(".model_version({:?})", model_version));
+    }
+    if let Some(prompt_id) = &args.prompt_id {
+        calls.push(format!(".prompt_id({:?})", prompt_id));
+    }
+    if let Some(attempt) = args.attempt.filter(|n| *n != args.status.attempt()) {
+        calls.push(format!(".attempt({})", attempt));
+    }
+    if let Some(notes) = &args.notes {
+        calls.push(format!(".notes({:?})", notes));
+    }
+
+    // the vector's elements are indented by 12 and rustfmt wraps at 100
+    let width = 100 - 12 - ",".len();
+    let call = format!("candidate!({})", arguments.join(", "));
+    let item = format!("{}{}", call, calls.concat());
+    if item.len() <= width {
+        return item;
+    }
+    let call = if call.len() <= width {
+        call
+    } else {
+        format!("candidate!(\n    {}\n)", arguments.join(",\n    "))
+    };
+    calls
+        .iter()
+        .fold(call, |item, c| format!("{}\n{}", item, c))
+}
+
+/// The indentation of the line containing byte `at`.
+fn indent_at(text: &str, at: usize) -> &str {
+    let line = &text[text[..at].rfind('\n').map_or(0, |i| i + 1)..];
+    &line[..line.len() - line.trim_start().len()]
+}
+
+fn getter(engine: &str, target: &Target, item: &str) -> String {
+    format!(
+        "pub fn {}() -> CandidateInfo<{}> {{\n    \
+         CandidateInfo::new(\n        \
+         {:?},\n        \
+         vec![\n            \
+         {},\n        \
+         ],\n    \
+         )\n\
+         }}\n",
+        target.getter,
+        target.task_type,
+        engine,
+        item.replace('\n', "\n            ")
+    )
+}
+
+/// Adds the getter of another task family to an existing module, after the
+/// getters it already has, and registers it.
+fn add_getter(source: &str, getter: &str, target: &Target) -> Result<String, String> {
+    let import = format!("use crate::task::{};", target.task_type);
+    let source = if source.contains(&import) {
+        source.to_string()
+    } else {
+        add_line_after_last(source, &["use "], &import)
+    };
+
+    let register = source.find("register!(").ok_or("no register!(...) line")? + "register!".len();
+    let close = register
+        + matching_close(&source[register..], '(', ')').ok_or("unterminated register!(...)")?;
+    let registered = source[..close].trim_end().trim_end_matches(',');
+    let separator = if registered.ends_with('(') { "" } else { ", " };
+    let source = format!(
+        "{}{}{} => {}{}",
+        registered,
+        separator,
+        target.task_type,
+        target.getter,
+        &source[close..]
+    );
+
+    let end = source.rfind("pub fn get_candidates").and_then(|at| {
+        let open = at + source[at..].find('{')?;
+        Some(open + matching_close(&source[open..], '{', '}')? + 1)
+    });
+    Ok(match end {
+        Some(end) => format!(
+            "{}\n\n{}{}",
+            &source[..end],
+            getter.trim_end(),
+            &source[end..]
+        ),
+        None => format!("{}\n{}", source.trim_end(), getter),
+    })
+}
+
+/// Inserts `line` after the last unindented line starting with one of `prefixes`.
+fn add_line_after_last(source: &str, prefixes: &[&str], line: &str) -> String {
+    let mut at = 0;
+    let mut offset = 0;
+    for existing in source.split_inclusive('\n') {
+        offset += existing.len();
+        if prefixes.iter().any(|prefix| existing.starts_with(prefix)) {
+            at = offset;
+        }
+    }
+    format!("{}{}\n{}", &source[..at], line, &source[at..])
+}
+
+fn new_module(engine: &str, target: &Target, file_stem: &str, item: &str) -> String {
+    format!(
+        "use crate::common::{{CandidateInfo, candidate}};\n\
+         use crate::registry::register;\n\
+         use crate::task::{};\n\
+         \n\
+         mod {};\n\
+         \n\
+         // created by `ingest`, which adds a file to this directory and an\n\
+         // entry to the vector below for every response it is given\n\
+         \n\
+         register!({} => {});\n\
+         \n\
+         {}",
+        target.task_type,
+        file_stem,
+        target.task_type,
+        target.getter,
+        getter(engine, target, item)
+    )
+}
+
+/// Inserts `mod name;` among the module declarations of main.rs, keeping them sorted.
+fn add_mod_declaration(main: &str, name: &str) -> Result<String, String> {
+    let declaration = format!("{}\nmod {};\n", CANDIDATE_MOD_ATTRIBUTE, name);
+    let mut offset = 0;
+    // start of the attributes above the current line
+    let mut item_start = None;
+    let mut last_mod = None;
+    for line in main.split_inclusive('\n') {
+        if line.starts_with("mod ") {
+            if line > format!("mod {};\n", name).as_str() {
+                let at = item_start.unwrap_or(offset);
+                return Ok(format!("{}{}{}", &main[..at], declaration, &main[at..]));
+            }
+            last_mod = Some(offset + line.len());
+        }
+        if line.starts_with("#[") {
+            item_start.get_or_insert(offset);
+        } else {
+            item_start = None;
+        }
+        offset += line.len();
+    }
+    let at = last_mod.ok_or("no module declarations")?;
+    Ok(format!("{}{}{}", &main[..at], declaration, &main[at..]))
+}
+
+/// The generated file: the answer's code, or a stub, with a header recording
+/// where it came from.
+fn candidate_source(
+    args: &IngestArgs,
+    target: &Target,
+    answer: Option<&Answer>,
+    date: NaiveDate,
+) -> String {
+    let mut source = format!(
+        "// {}, {}, for the task \"{}\"\n",
+        args.engine, date, target.task_name
+    );
+    source.push_str("// prompt:\n");
+    for paragraph in args.prompt.as_deref().unwrap_or(STANDARD_PROMPT).lines() {
+        let mut line = String::from("//  ");
+        for word in paragraph.split_whitespace() {
+            if line.len() + word.len() > 79 {
+                source.push_str(&line);
+                source.push('\n');
+                line = String::from("//  ");
+            }
+            line.push(' ');
+            line.push_str(word);
+        }
+        source.push_str(line.trim_end());
+        source.push('\n');
+    }
+    source.push_str(&format!(
+        "// ingested from {}\n",
+        args.response
+            .file_name()
+            .map_or(args.response.to_string_lossy(), |name| name
+                .to_string_lossy())
+    ));
+
+    match answer {
+        Some(answer) => {
+            source.push_str(&format!(
+                "// unchanged except that {} is made public and not inlined\n\
+                 #![allow(dead_code, unused, clippy::all)]\n\n",
+                answer.function_name()
+            ));
+            source.push_str(&answer.source());
+        }
+        None => {
+            let (params, output) = target.signature.rsplit_once("->").unwrap_or_default();
+            let params: Vec<String> = split_top_level(params.trim().trim_matches(['(', ')']))
+                .iter()
+                .enumerate()
+                .map(|(i, ty)| format!("_arg{}: {}", i, ty))
+                .collect();
+            source.push_str(&format!(
+                "// the AI gave no code that compiles, so this is a stub\n\
+                 \n\
+                 #[inline(never)]\n\
+                 pub fn {}({}) -> {} {{\n    \
+                 Default::default()\n\
+                 }}\n",
+                target.function_name,
+                params.join(", "),
+                output.trim()
+            ));
+        }
+    }
+    source
+}
+
+fn read(path: &Path) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn write(path: &Path, contents: &str) -> Result<(), String> {
+    fs::write(path, contents).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::AICodeGenStatus;
+
+    fn string_distance() -> Target {
+        Target {
+            task_name: "levenshstein distance".to_string(),
+            function_name: "levenshtein_distance".to_string(),
+            signature: "(&str, &str) -> usize",
+            task_type: "StringDistance",
+            getter: "get_candidates",
+        }
+    }
+
+    fn u64_function() -> Target {
+        Target {
+            task_name: "count of primes".to_string(),
+            function_name: "count_primes".to_string(),
+            signature: "(u64) -> u64",
+            task_type: "U64Function",
+            getter: "get_candidates2",
+        }
+    }
+
+    fn ingest_args(status: AICodeGenStatus) -> IngestArgs {
+        IngestArgs {
+            response: PathBuf::from("answer.md"),
+            engine: "Mistral Large 2".to_string(),
+            task: "count of primes".to_string(),
+            date: None,
+            prompt: None,
+            prompt_id: None,
+            status,
+            attempt: None,
+            model_version: None,
+            notes: None,
+            module: None,
+            src: PathBuf::from("src"),
+        }
+    }
+
+    fn date() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 3, 4).unwrap()
+    }
+
+    #[test]
+    fn code_blocks_keeps_rust_and_untagged_fences() {
+        let text = "Here:\n\
+                    ```rust\nfn a() {}\n```\n\
+                    ~~~rs\nfn b() {}\n~~~\n\
+                    ```python\nprint(1)\n```\n\
+                    ```\nfn c() {}\n```\n\
+                    ````Rust\n```\nfn d() {}\n````\n\
+                    ``not a fence\n";
+        assert_eq!(
+            code_blocks(text),
+            [
+                "fn a() {}\n",
+                "fn b() {}\n",
+                "fn c() {}\n",
+                "```\nfn d() {}\n"
+            ]
+        );
+    }
+
+    #[test]
+    fn normalize_signature_drops_names_whitespace_and_lifetimes() {
+        assert_eq!(
+            normalize_signature("(a: &'a str, b: &'b str) -> usize {"),
+            "(&str,&str)->usize"
+        );
+        assert_eq!(
+            normalize_signature("(n: u64, pairs: HashMap<u64, (u32, u32)>) -> Vec<u64> where"),
+            "(u64,HashMap<u64,(u32,u32)>)->Vec<u64>"
+        );
+        assert_eq!(
+            normalize_signature("(s: std::string::String,)"),
+            "(std::string::String)->()"
+        );
+        assert_eq!(
+            normalize_signature("(&self, x: impl Into<u64>) -> u64;"),
+            "(&self,implInto<u64>)->u64"
+        );
+    }
+
+    #[test]
+    fn functions_parse_generic_headers() {
+        let code = "pub fn levenshtein_distance<'a, T: AsRef<str>>(a: &'a str, b: &'a str) -> usize where T: Copy {\n    0\n}\n    fn nested(n: u64) -> u64 { n }\n";
+        let found = functions(code);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].name, "levenshtein_distance");
+        assert_eq!(found[0].signature, "(&str,&str)->usize");
+        assert_eq!((found[0].line_start, found[0].fn_start), (0, 4));
+    }
+
+    #[test]
+    fn find_candidate_prefers_the_baseline_name_then_the_last_block() {
+        let target = string_distance();
+        let blocks = [
+            "fn levenshtein_distance(a: &str, b: &str) -> usize { 1 }\n".to_string(),
+            "fn helper(a: &str, b: &str) -> usize { 2 }\n\
+             fn levenshtein_distance(s: &str, t: &str) -> usize { helper(s, t) }\n"
+                .to_string(),
+            "fn other(a: &str, b: &str) -> usize { 3 }\n".to_string(),
+        ];
+        let (block, function) = find_candidate(&blocks, &target).unwrap();
+        assert_eq!(block, blocks[1]);
+        assert_eq!(function.name, "levenshtein_distance");
+
+        // one name, whatever it is, in several blocks: the last one
+        let blocks = [
+            "fn distance(a: &str, b: &str) -> usize { 1 }\n".to_string(),
+            "fn distance(a: &str, b: &str) -> usize { 2 }\n".to_string(),
+        ];
+        let (block, function) = find_candidate(&blocks, &target).unwrap();
+        assert_eq!(block, blocks[1]);
+        assert_eq!(function.name, "distance");
+
+        let blocks = ["fn distance(a: &str, b: &str) -> usize { 1 }\n\
+             fn other(a: &str, b: &str) -> usize { 2 }\n"
+            .to_string()];
+        assert_eq!(
+            find_candidate(&blocks, &target).err().unwrap(),
+            "more than one function with the signature (&str, &str) -> usize: distance, other"
+        );
+
+        let blocks = ["fn distance(a: &[u8], b: &[u8]) -> usize { 1 }\n".to_string()];
+        assert_eq!(
+            find_candidate(&blocks, &target).err().unwrap(),
+            "no function with the signature (&str, &str) -> usize in the Rust code blocks"
+        );
+    }
+
+    #[test]
+    fn insert_vec_item_appends_to_a_vector_one_element_per_line() {
+        let source = "fn f() {\n    g(\n        \"x\",\n        vec![\n            a,\n            b\n        ],\n    )\n}\n";
+        assert_eq!(
+            insert_vec_item(source, 0, "c(\n    1,\n)").unwrap(),
+            "fn f() {\n    g(\n        \"x\",\n        vec![\n            a,\n            b,\n            c(\n                1,\n            ),\n        ],\n    )\n}\n"
+        );
+    }
+
+    #[test]
+    fn insert_vec_item_splits_a_one_line_vector() {
+        let source =
+            "fn f() {\n    g(\"x\", vec![candidate!(T, \"t\", a, \"2025-01-02\", Ok)])\n}\n";
+        assert_eq!(
+            insert_vec_item(source, 0, "candidate!(T, \"t\", b, \"2025-03-04\", Ok)").unwrap(),
+            "fn f() {\n    g(\"x\", vec![\n        candidate!(T, \"t\", a, \"2025-01-02\", Ok),\n        candidate!(T, \"t\", b, \"2025-03-04\", Ok),\n    ])\n}\n"
+        );
+
+        let empty = "fn f() {\n    g(vec![])\n}\n";
+        assert_eq!(
+            insert_vec_item(empty, 0, "a").unwrap(),
+            "fn f() {\n    g(vec![\n        a,\n    ])\n}\n"
+        );
+
+        assert!(insert_vec_item("fn f() {}\n", 0, "a").is_err());
+    }
+
+    #[test]
+    fn insert_vec_item_starts_at_the_getter() {
+        let source = "fn f() { vec![a] }\nfn g() { vec![b] }\n";
+        let at = source.find("fn g").unwrap();
+        assert_eq!(
+            insert_vec_item(source, at, "c").unwrap(),
+            "fn f() { vec![a] }\nfn g() { vec![\n    b,\n    c,\n] }\n"
+        );
+    }
+
+    #[test]
+    fn add_getter_registers_a_second_family() {
+        let target = u64_function();
+        let source = "use crate::common::{CandidateInfo, candidate};\n\
+                      use crate::registry::register;\n\
+                      use crate::task::StringDistance;\n\
+                      \n\
+                      mod levenshtein_2025_01_02;\n\
+                      \n\
+                      register!(StringDistance => get_candidates);\n\
+                      \n\
+                      pub fn get_candidates() -> CandidateInfo<StringDistance> {\n    \
+                      CandidateInfo::new(\"engine\", vec![])\n\
+                      }\n\
+                      \n\
+                      fn unrelated() {}\n";
+        let getter = getter(
+            "engine",
+            &target,
+            "candidate!(U64Function, \"count of primes\", count_primes_2025_03_04::count_primes, \"2025-03-04\", Ok)",
+        );
+        let updated = add_getter(source, &getter, &target).unwrap();
+
+        assert!(
+            updated.contains("use crate::task::StringDistance;\nuse crate::task::U64Function;\n")
+        );
+        assert!(updated.contains(
+            "register!(StringDistance => get_candidates, U64Function => get_candidates2);"
+        ));
+        assert!(updated.contains(&format!(
+            "    CandidateInfo::new(\"engine\", vec![])\n}}\n\n{}\nfn unrelated() {{}}\n",
+            getter
+        )));
+
+        // without getters to follow, it goes at the end; the import is not repeated
+        let source = "use crate::task::U64Function;\n\nregister!();\n";
+        assert_eq!(
+            add_getter(source, &getter, &target).unwrap(),
+            format!(
+                "use crate::task::U64Function;\n\nregister!(U64Function => get_candidates2);\n{}",
+                getter
+            )
+        );
+    }
+
+    #[test]
+    fn add_mod_declaration_keeps_the_declarations_sorted() {
+        let main = "mod cli;\n\
+                    #[allow(clippy::manual_is_multiple_of)]\n\
+                    mod module_baseline;\n\
+                    #[allow(clippy::manual_is_multiple_of)]\n\
+                    mod module_openai;\n\
+                    mod task;\n\
+                    \n\
+                    fn main() {}\n";
+        assert_eq!(
+            add_mod_declaration(main, "module_mistral").unwrap(),
+            "mod cli;\n\
+             #[allow(clippy::manual_is_multiple_of)]\n\
+             mod module_baseline;\n\
+             #[allow(clippy::manual_is_multiple_of)]\n\
+             mod module_mistral;\n\
+             #[allow(clippy::manual_is_multiple_of)]\n\
+             mod module_openai;\n\
+             mod task;\n\
+             \n\
+             fn main() {}\n"
+        );
+        assert_eq!(
+            add_mod_declaration("mod a;\n\nfn main() {}\n", "module_z").unwrap(),
+            "mod a;\n#[allow(clippy::manual_is_multiple_of)]\nmod module_z;\n\nfn main() {}\n"
+        );
+        assert!(add_mod_declaration("fn main() {}\n", "module_z").is_err());
+    }
+
+    #[test]
+    fn new_module_registers_its_getter() {
+        let module = new_module(
+            "Mistral Large 2",
+            &u64_function(),
+            "count_primes_2025_03_04",
+            "candidate!(U64Function, \"count of primes\", count_primes_2025_03_04::count_primes, \"2025-03-04\", Ok)",
+        );
+        assert!(module.contains("use crate::task::U64Function;\n\nmod count_primes_2025_03_04;\n"));
+        assert!(module.contains("register!(U64Function => get_candidates2);\n"));
+        assert!(module.contains(
+            "pub fn get_candidates2() -> CandidateInfo<U64Function> {\n    CandidateInfo::new(\n        \"Mistral Large 2\",\n"
+        ));
+    }
+
+    #[test]
+    fn candidate_item_fits_on_one_line_when_it_can() {
+        let target = u64_function();
+        let args = ingest_args(AICodeGenStatus::Ok);
+        assert_eq!(
+            candidate_item(&args, &target, None, "primes", date()),
+            "candidate!(U64Function, \"count of primes\", primes::count_primes, \"2025-03-04\", Ok)"
+        );
+    }
+
+    #[test]
+    fn candidate_item_wraps_long_calls() {
+        let target = u64_function();
+        let mut args = ingest_args(AICodeGenStatus::SecondTryOk);
+        args.model_version = Some("Large 2".to_string());
+        args.notes = Some("fixed after the compiler errors".to_string());
+        assert_eq!(
+            candidate_item(&args, &target, None, "count_primes_2025_03_04", date()),
+            "candidate!(\n    \
+             U64Function,\n    \
+             \"count of primes\",\n    \
+             count_primes_2025_03_04::count_primes,\n    \
+             \"2025-03-04\",\n    \
+             SecondTryOk\n\
+             )\n\
+             .model_version(\"Large 2\")\n\
+             .notes(\"fixed after the compiler errors\")"
+        );
+
+        // an attempt other than the status's is recorded
+        let mut args = ingest_args(AICodeGenStatus::Ok);
+        args.attempt = Some(3);
+        assert_eq!(
+            candidate_item(&args, &target, None, "p", date()),
+            "candidate!(U64Function, \"count of primes\", p::count_primes, \"2025-03-04\", Ok)\n.attempt(3)"
+        );
+    }
+
+    #[test]
+    fn answer_source_makes_the_function_public_and_not_inlined() {
+        let response = "```rust\n\
+                        use std::cmp::min;\n\
+                        \n\
+                        #[inline(always)]\n\
+                        fn helper(n: u64) -> u64 { n }\n\
+                        \n\
+                        /// Counts them.\n\
+                        #[inline]\n\
+                        #[must_use]\n\
+                        pub(crate) fn count_primes(n: u64) -> u64 {\n    \
+                        helper(n)\n\
+                        }\n\
+                        ```\n";
+        let answer = find_answer(response, &u64_function()).unwrap();
+        assert_eq!(answer.function_name(), "count_primes");
+        assert_eq!(
+            answer.source(),
+            "use std::cmp::min;\n\
+             \n\
+             #[inline(always)]\n\
+             fn helper(n: u64) -> u64 { n }\n\
+             \n\
+             /// Counts them.\n\
+             #[must_use]\n\
+             #[inline(never)]\n\
+             pub fn count_primes(n: u64) -> u64 {\n    \
+             helper(n)\n\
+             }\n"
+        );
+
+        let response = "```rust\nconst fn count_primes(n: u64) -> u64 { n }\n```\n";
+        let answer = find_answer(response, &u64_function()).unwrap();
+        assert_eq!(
+            answer.source(),
+            "#[inline(never)]\npub const fn count_primes(n: u64) -> u64 { n }\n"
+        );
+
+        assert_eq!(
+            find_answer("no code here", &u64_function()).err().unwrap(),
+            "no Rust code blocks found"
+        );
+    }
+}
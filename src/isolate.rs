@@ -0,0 +1,470 @@
+use std::fmt;
+use std::io::{BufRead, BufReader};
+use std::panic;
+use std::panic::AssertUnwindSafe;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::cli::Timing;
+use crate::common;
+use crate::common::CandidateInfo;
+use crate::environment;
+use crate::heap::{self, Allocations};
+use crate::latency::{self, Histogram, Latency};
+use crate::perf::{self, Counters};
+use crate::stats::Summary;
+use crate::task;
+use crate::task::Task;
+use crate::verify;
+
+// Each candidate is verified and timed in a child process, a re-exec of this
+// binary with a hidden subcommand, so that a call that never returns (the
+// baseline fibonacci on a large n, for example) costs one Timeout row instead
+// of hanging the whole suite, and a panic, out-of-bounds read or segfault in
+// an unsafe candidate costs one Panicked or Crashed row instead of the run.
+// The child reports back on stdout, one "key value" line per field behind a
+// marker (see `report`), so that whatever the candidate prints is skipped.
+
+/// Hidden subcommand the harness re-executes itself with to run one candidate.
+pub const CHILD_COMMAND: &str = "__run-candidate";
+
+/// Wall-clock and CPU time allowed on top of the caching check and the timing
+/// window (`--duration` each), for process start-up and verification.
+const TIME_SLACK: Duration = Duration::from_secs(10);
+
+/// Calls of the benchmark input allowed on top of that: the caching check,
+/// the timing and the counted runs each make a few calls more than their
+/// budget when one call is long. The child reports how long its first call
+/// took on a "call" line, and the limits grow by this many such calls.
+const SLOW_CALLS: f64 = 20.0;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// About how long the candidate runs after timing under the hardware
+/// counters, and again counting allocations, and again timing every call.
+const COUNTED_SECONDS: f64 = 0.2;
+
+/// Exit code of a child whose candidate panicked; the panic message is on its "panic" line.
+const PANIC_EXIT_CODE: i32 = 101;
+
+/// Starts every line of a child's report: an ASCII record separator, which
+/// candidates have no reason to print, and a word to make it readable.
+pub const REPORT_MARK: &str = "\u{1e}report ";
+
+/// What came back from running one candidate in a child process.
+#[allow(clippy::large_enum_variant)] // one per child process
+pub enum Outcome {
+    Finished {
+        /// None for the baseline, which is not verified against itself.
+        matches: Option<bool>,
+        /// Whether the candidate answers repeated inputs from a cache; always
+        /// false for the baseline.
+        caches: bool,
+        result: String,
+        summary: Summary,
+        /// Per-call hardware counts, when asked for with --counters.
+        counters: Option<Counters>,
+        /// Allocations per call, when asked for with --allocations.
+        allocations: Option<Allocations>,
+        /// Per-call latency percentiles, when asked for with --latency.
+        latency: Option<Latency>,
+    },
+    Timeout,
+    Panicked(String),
+    Crashed(String),
+    Failed(String),
+}
+
+/// Runs function `function` of module `module` of the task `family` in a child
+/// process, killing it if it outlives the time limit.
+pub fn run_candidate(family: &str, module: usize, function: usize, timing: &Timing) -> Outcome {
+    let mut args = vec![
+        CHILD_COMMAND.to_string(),
+        family.to_string(),
+        module.to_string(),
+        function.to_string(),
+    ];
+    args.extend(timing.to_args());
+
+    match run_child(&args, time_limit(timing, 0.0)) {
+        Ok(output) => parse_output(&output)
+            .unwrap_or_else(|| Outcome::Failed("malformed child output".to_string())),
+        Err((outcome, _)) => outcome,
+    }
+}
+
+/// Wall-clock and CPU time a child may take when one call of its candidate
+/// takes `call_seconds`.
+fn time_limit(timing: &Timing, call_seconds: f64) -> Duration {
+    Duration::from_secs(2 * timing.duration)
+        + TIME_SLACK
+        + Duration::from_secs_f64(call_seconds * SLOW_CALLS)
+}
+
+/// Re-executes this binary with `args`, killing it if it outlives `limit`,
+/// extended by SLOW_CALLS times the time of one call when the child reports
+/// it on a "call" line. Returns what it printed if it exited successfully,
+/// and otherwise how it ended (never `Outcome::Finished`) together with what
+/// it printed until then.
+#[allow(clippy::result_large_err)] // once per child process
+pub fn run_child(args: &[String], limit: Duration) -> Result<String, (Outcome, String)> {
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => {
+            return Err((
+                Outcome::Failed(format!("cannot locate own executable: {}", e)),
+                String::new(),
+            ));
+        }
+    };
+
+    let mut child = match Command::new(exe)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            return Err((
+                Outcome::Failed(format!("cannot start child process: {}", e)),
+                String::new(),
+            ));
+        }
+    };
+
+    // read on another thread so a chatty child can never block on a full pipe
+    let stdout = child.stdout.take().unwrap();
+    let (call_sender, calls) = mpsc::channel::<f64>();
+    let reader = thread::spawn(move || {
+        let mut output = String::new();
+        for line in BufReader::new(stdout).lines() {
+            let Ok(line) = line else { break };
+            if let Some(("call", seconds)) = report_line(&line)
+                && let Ok(seconds) = seconds.parse()
+            {
+                let _ = call_sender.send(seconds);
+            }
+            output.push_str(&line);
+            output.push('\n');
+        }
+        output
+    });
+
+    let start_time = Instant::now();
+    let mut limit = limit;
+    let status = loop {
+        for seconds in calls.try_iter() {
+            limit += Duration::from_secs_f64(seconds * SLOW_CALLS);
+        }
+        match try_wait(&mut child) {
+            Ok(Some(exit)) => break Ok(exit),
+            Ok(None) if start_time.elapsed() >= limit => {
+                let _ = child.kill();
+                let _ = child.wait();
+                break Err(Outcome::Timeout);
+            }
+            Ok(None) => thread::sleep(POLL_INTERVAL),
+            Err(e) => {
+                let _ = child.kill();
+                break Err(Outcome::Failed(format!(
+                    "cannot wait for child process: {}",
+                    e
+                )));
+            }
+        }
+    };
+    let output = reader.join().unwrap_or_default();
+    let (status, cpu_time) = match status {
+        Ok(exit) => exit,
+        Err(outcome) => return Err((outcome, output)),
+    };
+
+    if exceeded_cpu_limit(&status, cpu_time, limit) {
+        return Err((Outcome::Timeout, output));
+    }
+    // with panic=abort the hook has reported the panic before the abort
+    let panic = output
+        .lines()
+        .filter_map(report_line)
+        .find_map(|(key, value)| (key == "panic").then_some(value));
+    if status.code() == Some(PANIC_EXIT_CODE) || (aborted(&status) && panic.is_some()) {
+        let message = panic.unwrap_or("unknown panic").to_string();
+        return Err((Outcome::Panicked(message), output));
+    }
+    if let Some(reason) = crash_reason(&status) {
+        return Err((Outcome::Crashed(reason), output));
+    }
+    if !status.success() {
+        return Err((
+            Outcome::Failed(format!("child process exited with {}", status)),
+            output,
+        ));
+    }
+    Ok(output)
+}
+
+/// Entry point of the child process: verifies and times one candidate and
+/// writes the outcome to stdout.
+pub fn child_main<T: Task>(
+    tasks: &[T],
+    modules: &[CandidateInfo<T>],
+    module: usize,
+    function: usize,
+    timing: &Timing,
+) {
+    if let Some(cpu) = timing.pin_cpu
+        && let Err(e) = environment::pin_to_cpu(cpu)
+    {
+        eprintln!("warning: {}", e);
+    }
+
+    // report the panic to the parent instead of printing it to the terminal
+    panic::set_hook(Box::new(|info| {
+        report("panic", info.to_string().replace('\n', " "));
+    }));
+
+    let run = AssertUnwindSafe(|| run_child_candidate(tasks, modules, module, function, timing));
+    if panic::catch_unwind(run).is_err() {
+        std::process::exit(PANIC_EXIT_CODE);
+    }
+}
+
+fn run_child_candidate<T: Task>(
+    tasks: &[T],
+    modules: &[CandidateInfo<T>],
+    module: usize,
+    function: usize,
+    timing: &Timing,
+) {
+    let candidate = &modules[module].candidates[function];
+    let task = task::find(tasks, candidate.task).expect("candidate does not belong to any task");
+    let f = candidate.function;
+
+    if module != 0
+        && let Some(matches) = verify::matches_baseline(task, &modules[0], f)
+    {
+        report("matches", matches);
+    }
+
+    let input = match timing.input_size {
+        Some(size) => task.sized_input(size),
+        None => task.benchmark_input(),
+    };
+    let start = Instant::now();
+    let output = T::call(f, &input);
+    let call_seconds = start.elapsed().as_secs_f64();
+    report("call", call_seconds);
+    // set once the length of a call is known: an unprivileged process cannot raise it
+    limit_cpu_time(time_limit(timing, call_seconds));
+
+    if module != 0 {
+        let budget = Duration::from_secs(timing.duration);
+        report("caches", verify::caches_results(task, f, &input, budget));
+    }
+    let inputs = task::input_pool(task, input, timing.input_pool.max(1));
+    let summary = common::run_for_duration::<T>(f, &inputs, timing);
+    report(
+        "summary",
+        format_args!(
+            "{} {} {} {} {} {} {}",
+            summary.samples,
+            summary.median,
+            summary.mean,
+            summary.std_dev,
+            summary.min,
+            summary.ci95_low,
+            summary.ci95_high
+        ),
+    );
+    report("result", task.format_output(&output));
+
+    let calls = (summary.median * COUNTED_SECONDS).ceil().max(1.0) as u64;
+    if timing.counters {
+        let counters = perf::count(common::call_in_rotation::<T>(f, &inputs), calls);
+        report("counters", counters.fields());
+    }
+    if timing.allocations {
+        let allocations = heap::count(common::call_in_rotation::<T>(f, &inputs), calls);
+        report("allocations", allocations.fields());
+    }
+    if timing.latency {
+        let histogram = latency::record(common::call_in_rotation::<T>(f, &inputs), calls);
+        report("latency", histogram.fields());
+    }
+}
+
+/// Writes one line of the child's report to stdout.
+pub fn report(key: &str, value: impl fmt::Display) {
+    println!("{}{} {}", REPORT_MARK, key, value);
+}
+
+/// The key and value of a line of the child's report, None for anything else
+/// the child printed. The marker may follow output the candidate did not end
+/// with a newline.
+pub fn report_line(line: &str) -> Option<(&str, &str)> {
+    let (_, report) = line.split_once(REPORT_MARK)?;
+    report.split_once(' ')
+}
+
+fn parse_output(output: &str) -> Option<Outcome> {
+    let mut matches = None;
+    let mut caches = false;
+    let mut result = None;
+    let mut summary = None;
+    let mut counters = None;
+    let mut allocations = None;
+    let mut latency = None;
+
+    for (key, value) in output.lines().filter_map(report_line) {
+        match key {
+            "matches" => matches = Some(value.parse().ok()?),
+            "caches" => caches = value.parse().ok()?,
+            // read by run_child as it arrives
+            "call" => {}
+            "counters" => counters = Some(Counters::parse(value)?),
+            "allocations" => allocations = Some(Allocations::parse(value)?),
+            "latency" => latency = Some(Histogram::parse(value)?.latency()),
+            "result" => result = Some(value.to_string()),
+            "summary" => {
+                let fields = value
+                    .split(' ')
+                    .map(str::parse)
+                    .collect::<Result<Vec<f64>, _>>()
+                    .ok()?;
+                let [samples, median, mean, std_dev, min, ci95_low, ci95_high] = fields[..] else {
+                    return None;
+                };
+                summary = Some(Summary {
+                    samples: samples as u32,
+                    median,
+                    mean,
+                    std_dev,
+                    min,
+                    ci95_low,
+                    ci95_high,
+                });
+            }
+            _ => return None,
+        }
+    }
+
+    Some(Outcome::Finished {
+        matches,
+        caches,
+        result: result?,
+        summary: summary?,
+        counters,
+        allocations,
+        latency,
+    })
+}
+
+/// Makes the kernel stop this process once it has used `limit` of CPU time.
+#[cfg(unix)]
+pub fn limit_cpu_time(limit: Duration) {
+    let limit = libc::rlimit {
+        rlim_cur: limit.as_secs() as libc::rlim_t,
+        rlim_max: limit.as_secs() as libc::rlim_t + 1,
+    };
+    unsafe {
+        libc::setrlimit(libc::RLIMIT_CPU, &limit);
+    }
+}
+
+#[cfg(not(unix))]
+pub fn limit_cpu_time(_limit: Duration) {}
+
+/// Reaps the child if it has exited, returning how it ended and the CPU time
+/// it used.
+#[cfg(unix)]
+fn try_wait(child: &mut Child) -> std::io::Result<Option<(ExitStatus, Duration)>> {
+    use std::os::unix::process::ExitStatusExt;
+    let mut status = 0;
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    let pid = unsafe {
+        libc::wait4(
+            child.id() as libc::pid_t,
+            &mut status,
+            libc::WNOHANG,
+            &mut usage,
+        )
+    };
+    let seconds = |time: libc::timeval| {
+        Duration::from_secs(time.tv_sec as u64) + Duration::from_micros(time.tv_usec as u64)
+    };
+    match pid {
+        0 => Ok(None),
+        -1 => Err(std::io::Error::last_os_error()),
+        _ => Ok(Some((
+            ExitStatus::from_raw(status),
+            seconds(usage.ru_utime) + seconds(usage.ru_stime),
+        ))),
+    }
+}
+
+#[cfg(not(unix))]
+fn try_wait(child: &mut Child) -> std::io::Result<Option<(ExitStatus, Duration)>> {
+    Ok(child.try_wait()?.map(|status| (status, Duration::ZERO)))
+}
+
+/// Whether the kernel stopped the child for using up its CPU time: SIGXCPU at
+/// the soft limit, or SIGKILL at the hard one. A SIGKILL before that came
+/// from elsewhere, most likely the out-of-memory killer.
+#[cfg(unix)]
+fn exceeded_cpu_limit(status: &ExitStatus, cpu_time: Duration, limit: Duration) -> bool {
+    use std::os::unix::process::ExitStatusExt;
+    match status.signal() {
+        Some(libc::SIGXCPU) => true,
+        Some(libc::SIGKILL) => cpu_time.as_secs() >= limit.as_secs(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn exceeded_cpu_limit(_status: &ExitStatus, _cpu_time: Duration, _limit: Duration) -> bool {
+    false
+}
+
+/// Describes how the child died if it was killed by a signal (or, on Windows,
+/// ended with an exception code) rather than exiting.
+#[cfg(unix)]
+fn crash_reason(status: &ExitStatus) -> Option<String> {
+    use std::os::unix::process::ExitStatusExt;
+    let signal = status.signal()?;
+    let name = match signal {
+        libc::SIGSEGV => "SIGSEGV (segmentation fault)",
+        libc::SIGBUS => "SIGBUS (bus error)",
+        libc::SIGILL => "SIGILL (illegal instruction)",
+        libc::SIGFPE => "SIGFPE (arithmetic exception)",
+        libc::SIGABRT => "SIGABRT (aborted, e.g. stack overflow)",
+        libc::SIGKILL => "SIGKILL (e.g. by the out-of-memory killer)",
+        _ => return Some(format!("killed by signal {}", signal)),
+    };
+    Some(format!("killed by {}", name))
+}
+
+/// Whether the child aborted, as a panic does under panic=abort.
+#[cfg(unix)]
+fn aborted(status: &ExitStatus) -> bool {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal() == Some(libc::SIGABRT)
+}
+
+#[cfg(not(unix))]
+fn aborted(status: &ExitStatus) -> bool {
+    crash_reason(status).is_some()
+}
+
+#[cfg(not(unix))]
+fn crash_reason(status: &ExitStatus) -> Option<String> {
+    // Windows reports exceptions such as access violations as NTSTATUS exit codes
+    match status.code() {
+        Some(code) if (code as u32) >= 0xC000_0000 => {
+            Some(format!("exception code {:#010X}", code as u32))
+        }
+        _ => None,
+    }
+}
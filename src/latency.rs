@@ -0,0 +1,166 @@
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+// Iterations per second is an average; a candidate whose cost depends on the
+// input (highly_composite searches an unbounded range) can have a good average
+// and a terrible tail. With --latency the child times every call of a separate
+// run after the timing window into a log-linear histogram, as HdrHistogram
+// does: exact below 32 ns, then 32 buckets per power of two, so every value is
+// known to within about 3% with a fixed 15 KiB of counts. Each call's time
+// includes reading the clock, some tens of nanoseconds. Use --input-pool for
+// calls on different inputs.
+
+/// Sub-buckets per power of two, as a power of two.
+const SUB_BUCKET_BITS: u32 = 5;
+const SUB_BUCKETS: u64 = 1 << SUB_BUCKET_BITS;
+const BUCKETS: usize = (SUB_BUCKETS + (64 - SUB_BUCKET_BITS as u64) * SUB_BUCKETS) as usize;
+
+/// Counts of per-call times in nanoseconds.
+pub struct Histogram {
+    counts: Vec<u64>,
+    max: u64,
+}
+
+impl Histogram {
+    pub fn new() -> Self {
+        Histogram {
+            counts: vec![0; BUCKETS],
+            max: 0,
+        }
+    }
+
+    pub fn record(&mut self, nanos: u64) {
+        self.counts[bucket(nanos)] += 1;
+        self.max = self.max.max(nanos);
+    }
+
+    /// Max and non-empty buckets as "max index:count ...", for the child's output.
+    pub fn fields(&self) -> String {
+        let mut fields = vec![self.max.to_string()];
+        fields.extend(
+            self.counts
+                .iter()
+                .enumerate()
+                .filter(|(_, count)| **count > 0)
+                .map(|(index, count)| format!("{}:{}", index, count)),
+        );
+        fields.join(" ")
+    }
+
+    pub fn parse(fields: &str) -> Option<Histogram> {
+        let mut fields = fields.split(' ');
+        let mut histogram = Histogram::new();
+        histogram.max = fields.next()?.parse().ok()?;
+        for field in fields {
+            let (index, count) = field.split_once(':')?;
+            *histogram.counts.get_mut(index.parse::<usize>().ok()?)? = count.parse().ok()?;
+        }
+        Some(histogram)
+    }
+
+    pub fn latency(&self) -> Latency {
+        Latency {
+            calls: self.counts.iter().sum(),
+            p50_ns: self.percentile(0.5),
+            p90_ns: self.percentile(0.9),
+            p99_ns: self.percentile(0.99),
+            p999_ns: self.percentile(0.999),
+            max_ns: self.max,
+            buckets: self
+                .counts
+                .iter()
+                .enumerate()
+                .filter(|(_, count)| **count > 0)
+                .map(|(index, count)| {
+                    let (low_ns, high_ns) = bounds(index);
+                    Bucket {
+                        low_ns,
+                        high_ns,
+                        count: *count,
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// Highest time of the bucket the `q` quantile falls in, capped at the max.
+    fn percentile(&self, q: f64) -> u64 {
+        let total: u64 = self.counts.iter().sum();
+        let target = ((q * total as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= target {
+                return bounds(index).1.min(self.max);
+            }
+        }
+        self.max
+    }
+}
+
+fn bucket(nanos: u64) -> usize {
+    if nanos < SUB_BUCKETS {
+        return nanos as usize;
+    }
+    let shift = 63 - nanos.leading_zeros() - SUB_BUCKET_BITS;
+    let sub = (nanos >> shift) - SUB_BUCKETS;
+    (SUB_BUCKETS + shift as u64 * SUB_BUCKETS + sub) as usize
+}
+
+/// Lowest and highest time counted in bucket `index`.
+fn bounds(index: usize) -> (u64, u64) {
+    let index = index as u64;
+    if index < SUB_BUCKETS {
+        return (index, index);
+    }
+    let shift = (index - SUB_BUCKETS) / SUB_BUCKETS;
+    let sub = (index - SUB_BUCKETS) % SUB_BUCKETS;
+    let low = (SUB_BUCKETS + sub) << shift;
+    (low, low + ((1u64 << shift) - 1))
+}
+
+/// Times each of `calls` calls of `f`.
+pub fn record<F: FnMut()>(mut f: F, calls: u64) -> Histogram {
+    let mut histogram = Histogram::new();
+    for _ in 0..calls {
+        let start = Instant::now();
+        f();
+        histogram.record(start.elapsed().as_nanos() as u64);
+    }
+    histogram
+}
+
+/// Tail percentiles of the per-call times of one candidate, with the
+/// histogram they come from for plotting.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Latency {
+    pub calls: u64,
+    pub p50_ns: u64,
+    pub p90_ns: u64,
+    pub p99_ns: u64,
+    pub p999_ns: u64,
+    pub max_ns: u64,
+    /// The non-empty buckets, fastest first.
+    pub buckets: Vec<Bucket>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Bucket {
+    pub low_ns: u64,
+    pub high_ns: u64,
+    pub count: u64,
+}
+
+/// A time in nanoseconds in the unit that suits it, e.g. 1.25 µs.
+pub fn format_nanos(nanos: u64) -> String {
+    let nanos = nanos as f64;
+    if nanos < 1e3 {
+        format!("{:.0} ns", nanos)
+    } else if nanos < 1e6 {
+        format!("{:.2} µs", nanos / 1e3)
+    } else if nanos < 1e9 {
+        format!("{:.2} ms", nanos / 1e6)
+    } else {
+        format!("{:.2} s", nanos / 1e9)
+    }
+}
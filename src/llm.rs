@@ -0,0 +1,155 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+// The engines `generate` can talk to. Anything that turns a conversation into
+// the next reply is a Client: a chat completions endpoint, or a list of saved
+// answers replayed in order so that the compile-and-retry loop can run
+// offline and reproducibly.
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    User,
+    Assistant,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Message {
+    pub role: Role,
+    pub content: String,
+}
+
+impl Message {
+    pub fn new(role: Role, content: String) -> Self {
+        Message { role, content }
+    }
+}
+
+pub trait Client {
+    /// Model the replies come from, recorded in the generated candidates.
+    fn model(&self) -> &str;
+
+    /// The model's reply to the conversation so far.
+    fn complete(&mut self, messages: &[Message]) -> Result<String, String>;
+}
+
+/// Any endpoint implementing OpenAI's chat completions API: OpenAI itself,
+/// DeepSeek, Mistral, OpenRouter, or a local llama.cpp, vLLM or Ollama server.
+pub struct OpenAiClient {
+    base_url: String,
+    model: String,
+    api_key: Option<String>,
+    agent: ureq::Agent,
+}
+
+/// Replays answers saved in files, one per call.
+pub struct FixtureClient {
+    model: String,
+    answers: Vec<PathBuf>,
+    next: usize,
+}
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(600);
+
+#[derive(Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    messages: &'a [Message],
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    choices: Vec<Choice>,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    message: ReplyMessage,
+}
+
+#[derive(Deserialize)]
+struct ReplyMessage {
+    content: Option<String>,
+}
+
+impl OpenAiClient {
+    pub fn new(base_url: &str, model: &str, api_key: Option<String>) -> Self {
+        let agent = ureq::Agent::config_builder()
+            .timeout_global(Some(REQUEST_TIMEOUT))
+            .http_status_as_error(false)
+            .build()
+            .into();
+        OpenAiClient {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model: model.to_string(),
+            api_key,
+            agent,
+        }
+    }
+}
+
+impl Client for OpenAiClient {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn complete(&mut self, messages: &[Message]) -> Result<String, String> {
+        let url = format!("{}/chat/completions", self.base_url);
+        let mut request = self.agent.post(&url);
+        if let Some(key) = &self.api_key {
+            request = request.header("Authorization", &format!("Bearer {}", key));
+        }
+
+        let mut response = request
+            .send_json(ChatRequest {
+                model: &self.model,
+                messages,
+            })
+            .map_err(|e| format!("{}: {}", url, e))?;
+        if !response.status().is_success() {
+            let body = response.body_mut().read_to_string().unwrap_or_default();
+            return Err(format!("{}: {} {}", url, response.status(), body.trim()));
+        }
+
+        let reply: ChatResponse = response
+            .body_mut()
+            .read_json()
+            .map_err(|e| format!("{}: {}", url, e))?;
+        reply
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|choice| choice.message.content)
+            .ok_or_else(|| format!("{}: the reply has no content", url))
+    }
+}
+
+impl FixtureClient {
+    pub fn new(model: &str, answers: Vec<PathBuf>) -> Self {
+        FixtureClient {
+            model: model.to_string(),
+            answers,
+            next: 0,
+        }
+    }
+}
+
+impl Client for FixtureClient {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn complete(&mut self, _messages: &[Message]) -> Result<String, String> {
+        let path = self.answers.get(self.next).ok_or_else(|| {
+            format!(
+                "asked for answer {} but only {} fixtures were given",
+                self.next + 1,
+                self.answers.len()
+            )
+        })?;
+        self.next += 1;
+        fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
@@ -1,202 +1,696 @@
 #![recursion_limit = "256"]
 
+mod cli;
+mod clock;
 mod common;
+mod environment;
+mod export;
+mod fingerprint;
+mod fuzz;
+mod generate;
+mod heap;
+mod history;
+mod ingest;
+mod isolate;
+mod latency;
+mod llm;
+mod matrix;
+// candidate modules are pasted verbatim from the AI, so newer lints are not applied to them
+#[allow(clippy::manual_is_multiple_of)]
 mod module_baseline;
+#[allow(clippy::manual_is_multiple_of)]
 mod module_claude;
+#[allow(clippy::manual_is_multiple_of)]
 mod module_copilot;
+#[allow(clippy::manual_is_multiple_of)]
 mod module_deepseek;
+#[allow(clippy::manual_is_multiple_of)]
 mod module_gemini;
+#[allow(clippy::manual_is_multiple_of)]
 mod module_grok;
+#[allow(clippy::manual_is_multiple_of)]
 mod module_openai;
+#[allow(clippy::manual_is_multiple_of)]
 mod module_synthaai;
+#[allow(clippy::manual_is_multiple_of)]
 mod module_watson;
+mod perf;
+mod readme;
+mod registry;
+mod stats;
+mod sweep;
+mod task;
+mod verify;
+
+use clap::Parser;
+use cli::{
+    Cli, Command, CompareArgs, Filter, FuzzArgs, GenerateArgs, IngestArgs, MatrixArgs, RunArgs,
+    SweepArgs, Timing, UpdateReadmeArgs,
+};
+use common::{AICodeGenStatus, CandidateInfo, ResultRow};
+use environment::Environment;
+use isolate::Outcome;
+use prettytable::{Cell, Row, Table};
+use registry::Registered;
+use std::collections::{BTreeMap, HashMap};
+use std::marker::PhantomData;
+use task::{StringDistance, Task, U64Function};
+
+/// Every family of tasks. A new one needs its `Task` and `registry::Registered`
+/// implementations and an entry here.
+const FAMILIES: &[&dyn Family] = &[
+    &FamilyOf::<StringDistance>(PhantomData),
+    &FamilyOf::<U64Function>(PhantomData),
+];
 
 fn main() {
-    let fun_duration = 2;
-    let input1 = "To be, or not to be, that is the question:
-                        Whether 'tis nobler in the mind to suffer
-                        The slings and arrows of outrageous fortune,
-                        Or to take arms against a sea of troubles,
-                        And by opposing end them: to die, to sleep
-                        No more; and by a sleep, to say we end
-                        The heart-ache, and the thousand natural shocks";
-
-    let input2 = "That Flesh is heir to? 'Tis a consummation:
-                        Devoutly to be wished. To die, to sleep,
-                        To sleep, perchance to Dream; aye, there's the rub;
-                        For in that sleep of death, what dreams may come,
-                        When we have shuffled off this mortal coil,
-                        Must give us pause.
-                         There's the respect
-                        That makes Calamity of so long life:
-                        For who would bear the Whips and Scorns of time,";
-
-    let modules = vec![
-        module_baseline::get_candidates(),
-        module_copilot::get_candidates(),
-        module_claude::get_candidates(),
-        module_gemini::get_candidates(),
-        module_openai::get_candidates(),
-        module_synthaai::get_candidates(),
-        module_grok::get_candidates(),
-        module_watson::get_candidates(),
-        module_deepseek::get_candidates(),
-    ];
+    match Cli::parse()
+        .command
+        .unwrap_or(Command::Run(RunArgs::default()))
+    {
+        Command::Run(args) => {
+            run(&args);
+        }
+        Command::List(filter) => list(&filter),
+        Command::UpdateReadme(args) => update_readme(&args),
+        Command::Compare(args) => compare(&args),
+        Command::Ingest(args) => ingest(&args),
+        Command::Generate(args) => generate(&args),
+        Command::Fuzz(args) => fuzz(&args),
+        Command::Sweep(args) => sweep(&args),
+        Command::Matrix(args) => matrix(&args),
+        Command::RunCandidate {
+            family,
+            module,
+            function,
+            timing,
+        } => run_child(&family, module, function, &timing),
+        Command::FuzzCandidate {
+            family,
+            module,
+            function,
+            seed,
+            cases,
+        } => fuzz_child(&family, module, function, seed, cases),
+    }
+}
 
-    let mut results = Vec::new();
+/// Verifies, times and prints the selected candidates, writing the exports asked for.
+fn run(args: &RunArgs) -> export::Report {
+    check_task_filter(&args.filter);
+    if let Some(cpu) = args.timing.pin_cpu
+        && !environment::cpu_allowed(cpu)
+    {
+        exit_with_error(&format!(
+            "cannot pin to CPU {}: not one this process may run on",
+            cpu
+        ));
+    }
+    let mut high_priority = args.high_priority;
+    if high_priority && let Err(e) = environment::raise_priority() {
+        eprintln!("warning: {}", e);
+        high_priority = false;
+    }
+    let environment = Environment::detect(args.timing.pin_cpu, high_priority);
+    print_fingerprint();
+    println!("Environment: {}\n", environment.describe());
+    for noise in environment.noise() {
+        eprintln!("warning: {}", noise);
+    }
+    if args.timing.counters
+        && let Err(e) = perf::probe()
+    {
+        eprintln!("warning: no hardware counters: {}", e);
+    }
+
+    let mut all_results: Vec<ResultRow> = Vec::new();
+    for family in FAMILIES {
+        let results = family.run(args).unwrap_or_else(|e| exit_with_error(&e));
+        common::print_sorted_results(&results, args.format);
+        all_results.extend(results);
+    }
+
+    let report = export::Report::new(&args.timing, environment, &all_results);
+    if let Some(path) = &args.json {
+        report
+            .write_json(path)
+            .unwrap_or_else(|e| exit_with_error(&format!("{}: {}", path.display(), e)));
+    }
+    if let Some(path) = &args.csv {
+        report
+            .write_csv(path)
+            .unwrap_or_else(|e| exit_with_error(&format!("{}: {}", path.display(), e)));
+    }
+    if !args.no_history {
+        history::append(&args.history, &report).unwrap_or_else(|e| exit_with_error(&e));
+    }
+    report
+}
+
+fn compare(args: &CompareArgs) {
+    let runs = history::load(&args.history).unwrap_or_else(|e| exit_with_error(&e));
+    if args.runs {
+        history::print_runs(&runs);
+        return;
+    }
+
+    let pick = |id: &Option<String>, from_end: usize| match id {
+        Some(id) => history::find_run(&runs, id),
+        None => runs
+            .len()
+            .checked_sub(from_end)
+            .map(|i| &runs[i])
+            .ok_or_else(|| "the history needs at least two runs to compare".to_string()),
+    };
+    let old = pick(&args.old, 2).unwrap_or_else(|e| exit_with_error(&e));
+    let new = pick(&args.new, 1).unwrap_or_else(|e| exit_with_error(&e));
+    history::print_comparison(old, new, &args.filter);
+}
+
+fn update_readme(args: &UpdateReadmeArgs) {
+    let report = match &args.from {
+        Some(path) => export::Report::read_json(path).unwrap_or_else(|e| exit_with_error(&e)),
+        None => run(&args.run),
+    };
+    readme::update(&args.readme, &report).unwrap_or_else(|e| exit_with_error(&e));
+    println!("updated {}", args.readme.display());
+}
+
+fn ingest(args: &IngestArgs) {
+    let target = FAMILIES
+        .iter()
+        .find_map(|family| family.ingest_target(&args.task))
+        .unwrap_or_else(|| exit_with_error(&unknown_task(&args.task)));
 
-    let baseline_result =
-        common::run_for_duration(modules[0].functions[0], input1, input2, fun_duration);
-    results.push((
-        modules[0].engine_name.to_string(),
-        modules[0].function_names[0].to_string(),
-        modules[0].dates[0],
-        modules[0].status[0],
-        baseline_result.0,
-        baseline_result.1,
-        "-----".to_string(),
-    ));
-
-    get_fun_results(
-        fun_duration,
-        input1,
-        input2,
-        modules,
-        &mut results,
-        baseline_result,
+    for path in ingest::ingest(args, &target).unwrap_or_else(|e| exit_with_error(&e)) {
+        println!("wrote {}", path.display());
+    }
+    println!(
+        "rebuild and run it with: cargo run --release -- run --engine \"{}\" --task \"{}\"",
+        args.engine, target.task_name
     );
+}
+
+fn generate(args: &GenerateArgs) {
+    let mut client: Box<dyn llm::Client> = if args.fixtures.is_empty() {
+        Box::new(llm::OpenAiClient::new(
+            &args.base_url,
+            &args.model,
+            std::env::var(&args.api_key_env).ok(),
+        ))
+    } else {
+        Box::new(llm::FixtureClient::new(&args.model, args.fixtures.clone()))
+    };
+
+    let (target, generated) = FAMILIES
+        .iter()
+        .find_map(|family| family.generate(client.as_mut(), args))
+        .unwrap_or_else(|| exit_with_error(&unknown_task(&args.task)));
+    let generated = generated.unwrap_or_else(|e| exit_with_error(&e));
+    println!("{}: {}", target.task_name, generated.status);
+
+    let engine = args.engine.clone().unwrap_or_else(|| args.model.clone());
+    let date = chrono::Local::now().date_naive();
+    let module = ingest::module_name(&args.src, &engine, args.module.as_deref())
+        .unwrap_or_else(|e| exit_with_error(&e));
+    let transcript = generate::save_transcript(
+        &args.responses,
+        &module,
+        &target,
+        date,
+        &generated.transcript,
+    )
+    .unwrap_or_else(|e| exit_with_error(&e));
+    println!("wrote {}", transcript.display());
+
+    let ingest_args = IngestArgs {
+        response: transcript,
+        engine,
+        task: target.task_name.clone(),
+        date: Some(date),
+        prompt: None,
+        prompt_id: None,
+        status: generated.status,
+        attempt: Some(generated.attempt),
+        model_version: Some(args.model.clone()),
+        notes: None,
+        module: args.module.clone(),
+        src: args.src.clone(),
+    };
+    for path in ingest::add_candidate(&ingest_args, &target, generated.answer.as_ref())
+        .unwrap_or_else(|e| exit_with_error(&e))
+    {
+        println!("wrote {}", path.display());
+    }
+}
+
+fn fuzz(args: &FuzzArgs) {
+    check_task_filter(&args.filter);
+    let seed = args.seed.unwrap_or_else(|| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64)
+    });
+    println!("seed {} (rerun with --seed {} to reproduce)", seed, seed);
+
+    let reports: Vec<fuzz::Report> = FAMILIES
+        .iter()
+        .flat_map(|family| family.fuzz(args, seed))
+        .collect();
+    fuzz::print_reports(&reports);
+}
+
+fn sweep(args: &SweepArgs) {
+    check_task_filter(&args.filter);
+
+    print_fingerprint();
+    println!();
+    let mut sweeps = Vec::new();
+    for family in FAMILIES {
+        sweeps.extend(family.sweep(args).unwrap_or_else(|e| exit_with_error(&e)));
+    }
+    sweep::print_sweeps(&sweeps, args.format);
+}
 
-    common::print_sorted_results(results);
-
-    // ---------------------------------------------------------------------------
-    //                type 2 functions
-    // ---------------------------------------------------------------------------
-
-    let modules2 = vec![
-        module_baseline::get_candidates2(),
-        module_copilot::get_candidates2(),
-        module_deepseek::get_candidates2(),
-        module_openai::get_candidates2(),
-        module_claude::get_candidates2(),
-        module_gemini::get_candidates2(),
-    ];
-
-    let mut results2 = Vec::new();
-
-    let baseline_result2 = common::run_for_duration2(modules2[0].functions[0], 1000, fun_duration);
-    results2.push((
-        modules2[0].engine_name.to_string(),
-        modules2[0].function_names[0].to_string(),
-        modules2[0].dates[0],
-        modules2[0].status[0],
-        baseline_result2.0,
-        baseline_result2.1,
-        "-----".to_string(),
-    ));
-
-    get_fun_results2(
-        fun_duration,
-        1000,
-        modules2,
-        &mut results2,
-        baseline_result2,
+fn matrix(args: &MatrixArgs) {
+    let profiles =
+        matrix::load_profiles(&args.config, &args.profiles).unwrap_or_else(|e| exit_with_error(&e));
+    let matrix = matrix::run_profiles(&profiles, args).unwrap_or_else(|e| exit_with_error(&e));
+
+    println!("Host: {}", export::Host::detect().describe());
+    matrix::print_matrix(&matrix, args.format).unwrap_or_else(|e| exit_with_error(&e));
+    if let Some(path) = &args.json {
+        matrix
+            .write_json(path)
+            .unwrap_or_else(|e| exit_with_error(&e));
+    }
+}
+
+/// Prints the host and build lines shown above the results.
+fn print_fingerprint() {
+    println!("Host: {}", export::Host::detect().describe());
+    println!("Build: {}", fingerprint::Build::current().describe());
+}
+
+/// Handles the hidden subcommand the fuzzer re-executes itself with.
+fn fuzz_child(family: &str, module: usize, function: usize, seed: u64, cases: u64) {
+    find_family(family).fuzz_child(module, function, seed, cases);
+}
+
+/// What the commands do with a family of tasks, the same for every family.
+trait Family: Sync {
+    /// `Registered::FAMILY`.
+    fn name(&self) -> &'static str;
+
+    fn task_names(&self) -> Vec<String>;
+
+    fn run(&self, args: &RunArgs) -> Result<Vec<ResultRow>, String>;
+
+    fn fuzz(&self, args: &FuzzArgs, seed: u64) -> Vec<fuzz::Report>;
+
+    fn sweep(&self, args: &SweepArgs) -> Result<Vec<sweep::Sweep>, String>;
+
+    /// Adds the family's candidates to the `list` table and the tasks each
+    /// engine attempted to `attempted`.
+    fn list(
+        &self,
+        table: &mut Table,
+        filter: &Filter,
+        attempted: &mut BTreeMap<String, Vec<String>>,
     );
 
-    common::print_sorted_results2(results2);
-}
-
-fn get_fun_results(
-    fun_duration: u64,
-    input1: &str,
-    input2: &str,
-    modules: Vec<common::CandidateInfo>,
-    results: &mut Vec<(
-        String,
-        String,
-        chrono::NaiveDate,
-        common::AICodeGenStatus,
-        usize,
-        f64,
-        String,
-    )>,
-    baseline_result: (usize, f64),
+    /// Where `ingest` puts a candidate of the task, if it is one of the family's.
+    fn ingest_target(&self, task_name: &str) -> Option<ingest::Target>;
+
+    /// Runs `generate` if the task is one of the family's.
+    fn generate(
+        &self,
+        client: &mut dyn llm::Client,
+        args: &GenerateArgs,
+    ) -> Option<(ingest::Target, Result<generate::Generated, String>)>;
+
+    fn run_child(&self, module: usize, function: usize, timing: &Timing);
+
+    fn fuzz_child(&self, module: usize, function: usize, seed: u64, cases: u64);
+}
+
+struct FamilyOf<T>(PhantomData<fn() -> T>);
+
+impl<T: Registered> Family for FamilyOf<T> {
+    fn name(&self) -> &'static str {
+        T::FAMILY
+    }
+
+    fn task_names(&self) -> Vec<String> {
+        T::tasks()
+            .iter()
+            .map(|task| task.name().to_string())
+            .collect()
+    }
+
+    fn run(&self, args: &RunArgs) -> Result<Vec<ResultRow>, String> {
+        get_fun_results(args, &T::tasks(), &modules::<T>())
+    }
+
+    fn fuzz(&self, args: &FuzzArgs, seed: u64) -> Vec<fuzz::Report> {
+        fuzz::fuzz_family(args, seed, T::FAMILY, &T::tasks(), &modules::<T>())
+    }
+
+    fn sweep(&self, args: &SweepArgs) -> Result<Vec<sweep::Sweep>, String> {
+        sweep::sweep_family(args, T::FAMILY, &T::tasks(), &modules::<T>())
+    }
+
+    fn list(
+        &self,
+        table: &mut Table,
+        filter: &Filter,
+        attempted: &mut BTreeMap<String, Vec<String>>,
+    ) {
+        let modules = modules::<T>();
+        add_list_rows(table, filter, &modules);
+        add_attempts(attempted, &modules);
+    }
+
+    fn ingest_target(&self, task_name: &str) -> Option<ingest::Target> {
+        T::tasks()
+            .iter()
+            .find(|task| task.name().eq_ignore_ascii_case(task_name))
+            .map(ingest::Target::new)
+    }
+
+    fn generate(
+        &self,
+        client: &mut dyn llm::Client,
+        args: &GenerateArgs,
+    ) -> Option<(ingest::Target, Result<generate::Generated, String>)> {
+        let tasks = T::tasks();
+        let task = tasks
+            .iter()
+            .find(|task| task.name().eq_ignore_ascii_case(&args.task))?;
+        let target = ingest::Target::new(task);
+        let baseline = &modules::<T>()[0];
+        let generated = baseline
+            .position(task.name())
+            .ok_or_else(|| format!("task '{}' has no baseline", task.name()))
+            .and_then(|k| {
+                generate::generate(
+                    client,
+                    task,
+                    baseline.candidates[k].function,
+                    &target,
+                    &args.scratch,
+                )
+            });
+        Some((target, generated))
+    }
+
+    fn run_child(&self, module: usize, function: usize, timing: &Timing) {
+        isolate::child_main(&T::tasks(), &modules::<T>(), module, function, timing);
+    }
+
+    fn fuzz_child(&self, module: usize, function: usize, seed: u64, cases: u64) {
+        fuzz::child_main(&T::tasks(), &modules::<T>(), module, function, seed, cases);
+    }
+}
+
+fn list(filter: &Filter) {
+    let mut table = Table::new();
+    table.set_format(*prettytable::format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+    table.set_titles(Row::new(vec![
+        Cell::new("AI Engine"),
+        Cell::new("Task"),
+        Cell::new("Function"),
+        Cell::new("Date"),
+        Cell::new("Declared Status"),
+        Cell::new("Attempt"),
+        Cell::new("Model Version"),
+        Cell::new("Prompt"),
+        Cell::new("Notes"),
+    ]));
+
+    let mut attempted = BTreeMap::new();
+    for family in FAMILIES {
+        family.list(&mut table, filter, &mut attempted);
+    }
+    table.printstd();
+
+    let mut gaps = Table::new();
+    gaps.set_format(*prettytable::format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+    gaps.set_titles(Row::new(vec![
+        Cell::new("AI Engine"),
+        Cell::new("Tasks Without an Attempt"),
+    ]));
+    for (engine_name, tasks) in attempted
+        .iter()
+        .filter(|(engine_name, _)| filter.engine_selected(engine_name))
+    {
+        let missing: Vec<String> = task_names()
+            .into_iter()
+            .filter(|task| filter.task_selected(task) && !tasks.contains(task))
+            .collect();
+        if !missing.is_empty() {
+            gaps.add_row(Row::new(vec![
+                Cell::new(engine_name),
+                Cell::new(&missing.join(", ")),
+            ]));
+        }
+    }
+    if !gaps.is_empty() {
+        println!();
+        gaps.printstd();
+    }
+}
+
+/// Collects the tasks each engine (but the baseline) has candidates for.
+fn add_attempts<T: Task>(
+    attempted: &mut BTreeMap<String, Vec<String>>,
+    modules: &[CandidateInfo<T>],
 ) {
-    modules.iter().enumerate().skip(1).for_each(|(_i, module)| {
-        for (j, function) in module.functions.iter().enumerate() {
-            let mod_result = common::run_for_duration(*function, input1, input2, fun_duration);
-
-            let speedup = if baseline_result.1 > 0.0 {
-                mod_result.1 / baseline_result.1
-            } else {
-                0.0 // Or handle this error case differently
-            };
+    for module in modules.iter().skip(1) {
+        attempted
+            .entry(module.engine_name.clone())
+            .or_default()
+            .extend(module.candidates.iter().map(|c| c.task.to_string()));
+    }
+}
 
-            if mod_result.0 == 0 {
-                results.push((
-                    module.engine_name.to_string(),
-                    module.function_names[j].to_string(),
-                    module.dates[j],
-                    module.status[j],
-                    mod_result.0,
-                    0.0,
-                    "none".to_string(),
-                ));
-            } else {
-                results.push((
-                    module.engine_name.to_string(),
-                    module.function_names[j].to_string(),
-                    module.dates[j],
-                    module.status[j],
-                    mod_result.0,
-                    mod_result.1,
-                    format!("{:.1}x", speedup),
-                ));
+fn add_list_rows<T: Task>(table: &mut Table, filter: &Filter, modules: &[CandidateInfo<T>]) {
+    for module in modules {
+        if !filter.engine_selected(&module.engine_name) {
+            continue;
+        }
+        for candidate in &module.candidates {
+            if filter.task_selected(candidate.task) {
+                table.add_row(Row::new(vec![
+                    Cell::new(&module.engine_name),
+                    Cell::new(candidate.task),
+                    Cell::new(candidate.function_name),
+                    Cell::new(&candidate.date.to_string()),
+                    Cell::new(&candidate.status.to_string()),
+                    Cell::new(&candidate.attempt.to_string()),
+                    Cell::new(candidate.model_version.unwrap_or("")),
+                    Cell::new(candidate.prompt_id.unwrap_or("")),
+                    Cell::new(candidate.notes.unwrap_or("")),
+                ]));
             }
         }
-    });
+    }
 }
 
-fn get_fun_results2(
-    fun_duration: u64,
-    input: u64,
-    modules: Vec<common::CandidateInfo2>,
-    results: &mut Vec<(
-        String,
-        String,
-        chrono::NaiveDate,
-        common::AICodeGenStatus,
-        u64,
-        f64,
-        String,
-    )>,
-    baseline_result: (u64, f64),
-) {
-    modules.iter().enumerate().skip(1).for_each(|(_i, module)| {
-        for (j, function) in module.functions.iter().enumerate() {
-            let mod_result = common::run_for_duration2(*function, input, fun_duration);
-            let speedup = mod_result.1 / baseline_result.1;
-
-            if mod_result.0 == 0 {
-                results.push((
-                    module.engine_name.to_string(),
-                    module.function_names[j].to_string(),
-                    module.dates[j],
-                    module.status[j],
-                    mod_result.0,
-                    0.0,
-                    "none".to_string(),
-                ));
-            } else {
-                results.push((
-                    module.engine_name.to_string(),
-                    module.function_names[j].to_string(),
-                    module.dates[j],
-                    module.status[j],
-                    mod_result.0,
-                    mod_result.1,
-                    format!("{:.1}x", speedup),
-                ));
+/// The registered candidates of family `T`, the baseline first.
+fn modules<T: Registered>() -> Vec<CandidateInfo<T>> {
+    registry::modules(&T::tasks()).unwrap_or_else(|e| exit_with_error(&e))
+}
+
+/// Handles the hidden subcommand the harness re-executes itself with.
+fn run_child(family: &str, module: usize, function: usize, timing: &Timing) {
+    find_family(family).run_child(module, function, timing);
+}
+
+fn find_family(name: &str) -> &'static dyn Family {
+    FAMILIES
+        .iter()
+        .copied()
+        .find(|family| family.name() == name)
+        .unwrap_or_else(|| exit_with_error(&format!("unknown task family '{}'", name)))
+}
+
+fn task_names() -> Vec<String> {
+    FAMILIES
+        .iter()
+        .flat_map(|family| family.task_names())
+        .collect()
+}
+
+/// Exits if the filter names a task that does not exist.
+fn check_task_filter(filter: &Filter) {
+    let task_names = task_names();
+    if let Some(unknown) = filter.tasks.iter().find(|name| {
+        !task_names
+            .iter()
+            .any(|task| task.eq_ignore_ascii_case(name))
+    }) {
+        exit_with_error(&unknown_task(unknown));
+    }
+}
+
+fn unknown_task(name: &str) -> String {
+    format!(
+        "unknown task '{}', expected one of: {}",
+        name,
+        task_names().join(", ")
+    )
+}
+
+fn exit_with_error(message: &str) -> ! {
+    eprintln!("error: {}", message);
+    std::process::exit(1);
+}
+
+/// Verifies and times every candidate of `modules` in a child process;
+/// `modules[0]` is the baseline. Each candidate's speedup is relative to the
+/// baseline of its own task.
+fn get_fun_results<T: Registered>(
+    args: &RunArgs,
+    tasks: &[T],
+    modules: &[CandidateInfo<T>],
+) -> Result<Vec<ResultRow>, String> {
+    let baseline = &modules[0];
+
+    let mut results = Vec::new();
+    let mut baseline_results = HashMap::new();
+
+    for task in tasks
+        .iter()
+        .filter(|task| args.filter.task_selected(task.name()))
+    {
+        let k = baseline
+            .position(task.name())
+            .ok_or_else(|| format!("task '{}' has no baseline", task.name()))?;
+
+        let mut row = ResultRow {
+            engine_name: baseline.engine_name.to_string(),
+            function_name: task.name().to_string(),
+            date: baseline.candidates[k].date,
+            status: baseline.candidates[k].status,
+            result: "0".to_string(),
+            summary: stats::Summary::default(),
+            speedup: None,
+            is_baseline: true,
+            counters: None,
+            allocations: None,
+            latency: None,
+        };
+
+        match isolate::run_candidate(T::FAMILY, 0, k, &args.timing) {
+            Outcome::Finished {
+                result,
+                summary,
+                counters,
+                allocations,
+                latency,
+                ..
+            } => {
+                row.result = result;
+                row.summary = summary;
+                row.counters = counters;
+                row.allocations = allocations;
+                row.latency = latency;
+                baseline_results.insert(task.name().to_string(), summary);
             }
+            outcome => record_failure(&mut row, outcome),
         }
-    });
+
+        results.push(row);
+    }
+
+    for (i, module) in modules.iter().enumerate().skip(1) {
+        if !args.filter.engine_selected(&module.engine_name) {
+            continue;
+        }
+
+        for (j, candidate) in module.candidates.iter().enumerate() {
+            if !args.filter.task_selected(candidate.task) {
+                continue;
+            }
+
+            let mut row = ResultRow {
+                engine_name: module.engine_name.to_string(),
+                function_name: candidate.task.to_string(),
+                date: candidate.date,
+                status: candidate.status,
+                result: "0".to_string(),
+                summary: stats::Summary::default(),
+                speedup: None,
+                is_baseline: false,
+                counters: None,
+                allocations: None,
+                latency: None,
+            };
+
+            if row.status.has_code() {
+                match isolate::run_candidate(T::FAMILY, i, j, &args.timing) {
+                    Outcome::Finished {
+                        matches,
+                        caches,
+                        result,
+                        summary,
+                        counters,
+                        allocations,
+                        latency,
+                    } => {
+                        let verdict = verify::Verdict {
+                            declared: row.status,
+                            observed: matches.map_or(row.status, |m| {
+                                verify::observed_status(row.status, m, caches)
+                            }),
+                        };
+                        verify::report_disagreement(&row.engine_name, &row.function_name, &verdict);
+
+                        row.status = verdict.observed;
+                        row.result = result;
+                        row.summary = summary;
+                        row.counters = counters;
+                        row.allocations = allocations;
+                        row.latency = latency;
+                        if let Some(baseline_summary) = baseline_results.get(&row.function_name) {
+                            row.speedup = Some(summary.speedup_over(baseline_summary));
+                        }
+                    }
+                    outcome => record_failure(&mut row, outcome),
+                }
+            }
+
+            results.push(row);
+        }
+    }
+
+    Ok(results)
+}
+
+/// Records a candidate that did not finish normally in its row.
+fn record_failure(row: &mut ResultRow, outcome: Outcome) {
+    let reason = match outcome {
+        Outcome::Finished { .. } => return,
+        Outcome::Timeout => {
+            row.status = AICodeGenStatus::Timeout;
+            return;
+        }
+        Outcome::Panicked(message) => {
+            row.status = AICodeGenStatus::Panicked;
+            message
+        }
+        Outcome::Crashed(reason) => {
+            row.status = AICodeGenStatus::Crashed;
+            reason
+        }
+        Outcome::Failed(reason) => {
+            row.status = AICodeGenStatus::Failed;
+            reason
+        }
+    };
+    eprintln!(
+        "warning: {} / {}: {}",
+        row.engine_name, row.function_name, reason
+    );
 }
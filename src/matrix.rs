@@ -0,0 +1,299 @@
+use prettytable::{Cell, Row, Table};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::cli::{Format, MatrixArgs};
+use crate::common::{self, AICodeGenStatus, ResultRow};
+use crate::export::{self, Report};
+
+// How much faster a candidate is than the baseline depends on how both are
+// compiled: an answer written for the autovectorizer only pulls ahead with
+// target-cpu=native, LTO and codegen-units=1 help whichever side calls small
+// helpers. `matrix` builds the harness once per profile of a JSON config file
+// (the release profile with the profile's overrides, each in its own target
+// directory under target/matrix so that rebuilding one does not invalidate the
+// others), runs every build, and prints one table per task with a candidate's
+// speedup under each profile and the spread between its best and worst.
+
+/// A named set of compiler settings, applied on top of the release profile.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    /// Passed as RUSTFLAGS, e.g. "-C target-cpu=native".
+    #[serde(default)]
+    pub rustflags: String,
+    pub opt_level: Option<String>,
+    /// "false", "thin", "fat" or "off".
+    pub lto: Option<String>,
+    pub codegen_units: Option<u32>,
+    /// "unwind" or "abort".
+    pub panic: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Config {
+    profiles: Vec<Profile>,
+}
+
+/// The results of every profile of one `matrix` run.
+#[derive(Serialize, Deserialize)]
+pub struct Matrix {
+    pub schema_version: u32,
+    pub profiles: Vec<ProfileResults>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ProfileResults {
+    pub profile: Profile,
+    pub report: Report,
+}
+
+/// Reads the profiles of `path`, keeping those named in `names` (all when empty).
+pub fn load_profiles(path: &Path, names: &[String]) -> Result<Vec<Profile>, String> {
+    let json = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let config: Config =
+        serde_json::from_str(&json).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    for profile in &config.profiles {
+        if profile.name.is_empty()
+            || !profile
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(format!(
+                "{}: profile name '{}' is not letters, digits, '-' and '_'",
+                path.display(),
+                profile.name
+            ));
+        }
+    }
+    if let Some(unknown) = names
+        .iter()
+        .find(|name| !config.profiles.iter().any(|p| p.name == **name))
+    {
+        return Err(format!(
+            "{}: no profile named '{}'",
+            path.display(),
+            unknown
+        ));
+    }
+
+    let profiles: Vec<Profile> = config
+        .profiles
+        .into_iter()
+        .filter(|profile| names.is_empty() || names.contains(&profile.name))
+        .collect();
+    if profiles.is_empty() {
+        return Err(format!("{}: no profiles", path.display()));
+    }
+    Ok(profiles)
+}
+
+/// Builds and runs the harness under every profile.
+pub fn run_profiles(profiles: &[Profile], args: &MatrixArgs) -> Result<Matrix, String> {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut results = Vec::new();
+
+    for profile in profiles {
+        let target_dir = manifest_dir
+            .join("target")
+            .join("matrix")
+            .join(&profile.name);
+        eprintln!("building profile {}", profile.name);
+        let binary = build(manifest_dir, &target_dir, profile)?;
+
+        eprintln!("running profile {}", profile.name);
+        let json = target_dir.join("results.json");
+        let mut command = Command::new(&binary);
+        command
+            .arg("run")
+            .arg(format!("--duration={}", args.duration))
+            .arg("--no-history")
+            .arg("--json")
+            .arg(&json)
+            .stdout(Stdio::null());
+        for engine in &args.filter.engines {
+            command.arg(format!("--engine={}", engine));
+        }
+        for task in &args.filter.tasks {
+            command.arg(format!("--task={}", task));
+        }
+        let status = command
+            .status()
+            .map_err(|e| format!("{}: {}", binary.display(), e))?;
+        if !status.success() {
+            return Err(format!(
+                "profile {}: the run failed ({})",
+                profile.name, status
+            ));
+        }
+
+        results.push(ProfileResults {
+            profile: profile.clone(),
+            report: Report::read_json(&json)?,
+        });
+    }
+
+    Ok(Matrix {
+        schema_version: export::SCHEMA_VERSION,
+        profiles: results,
+    })
+}
+
+/// Builds the harness in release mode with the overrides of `profile` and
+/// returns the path of the binary.
+fn build(manifest_dir: &Path, target_dir: &Path, profile: &Profile) -> Result<PathBuf, String> {
+    let mut command = Command::new("cargo");
+    command
+        .args(["build", "--release", "--quiet", "--color", "never"])
+        .arg("--manifest-path")
+        .arg(manifest_dir.join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(target_dir)
+        // takes precedence over RUSTFLAGS, so it must not leak in from a cargo run
+        .env_remove("CARGO_ENCODED_RUSTFLAGS")
+        .env("RUSTFLAGS", &profile.rustflags);
+    let overrides = [
+        ("CARGO_PROFILE_RELEASE_OPT_LEVEL", profile.opt_level.clone()),
+        ("CARGO_PROFILE_RELEASE_LTO", profile.lto.clone()),
+        (
+            "CARGO_PROFILE_RELEASE_CODEGEN_UNITS",
+            profile.codegen_units.map(|units| units.to_string()),
+        ),
+        ("CARGO_PROFILE_RELEASE_PANIC", profile.panic.clone()),
+    ];
+    for (variable, value) in overrides {
+        match value {
+            Some(value) => command.env(variable, value),
+            None => command.env_remove(variable),
+        };
+    }
+
+    let output = command
+        .output()
+        .map_err(|e| format!("cannot run cargo: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "profile {}: the build failed:\n{}",
+            profile.name,
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    Ok(target_dir.join("release").join(format!(
+        "{}{}",
+        env!("CARGO_PKG_NAME"),
+        std::env::consts::EXE_SUFFIX
+    )))
+}
+
+impl Matrix {
+    pub fn write_json(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(path, json + "\n").map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+/// Prints how each profile was built, then one table per task: the
+/// candidates' speedup under each profile (the baseline's iterations per
+/// second) and the ratio of their best speedup to their worst.
+pub fn print_matrix(matrix: &Matrix, output_format: Format) -> Result<(), String> {
+    let mut profiles = Vec::new();
+    for results in &matrix.profiles {
+        println!(
+            "Profile {}: {}",
+            results.profile.name,
+            results.report.build.describe()
+        );
+        let rows = results
+            .report
+            .results
+            .iter()
+            .map(ResultRow::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        profiles.push(rows);
+    }
+    let Some(first) = profiles.first() else {
+        return Ok(());
+    };
+    // answers that compiled at the second attempt are timed like the others
+    let timed = |result: &ResultRow| {
+        matches!(
+            result.status,
+            AICodeGenStatus::Ok | AICodeGenStatus::SecondTryOk
+        )
+    };
+
+    for (function_name, function_results) in common::sort_results(first) {
+        match output_format {
+            Format::Table => println!("\n\nSpeedup by profile for function: {}", function_name),
+            Format::Markdown => println!("\n### {}\n", function_name),
+        }
+
+        let mut table = Table::new();
+        table.set_format(common::table_format(output_format));
+        let mut titles = vec![Cell::new("AI Engine"), Cell::new("Date")];
+        titles.extend(
+            matrix
+                .profiles
+                .iter()
+                .map(|results| Cell::new(&results.profile.name)),
+        );
+        titles.push(Cell::new("Spread"));
+        table.set_titles(Row::new(titles));
+
+        for row in function_results {
+            let same_candidate = |other: &&ResultRow| {
+                other.engine_name == row.engine_name
+                    && other.function_name == row.function_name
+                    && other.date == row.date
+            };
+            let cells: Vec<Option<&ResultRow>> = profiles
+                .iter()
+                .map(|rows| rows.iter().find(same_candidate))
+                .collect();
+
+            let mut table_row = vec![
+                Cell::new(&row.engine_name),
+                Cell::new(&row.date.to_string()),
+            ];
+            table_row.extend(cells.iter().map(|cell| {
+                Cell::new(&match cell {
+                    None => "-".to_string(),
+                    Some(result) if result.is_baseline => {
+                        format!("{:.2} it/s", result.summary.median)
+                    }
+                    Some(result) if !timed(result) => {
+                        format!("{:?}", result.status)
+                    }
+                    Some(result) => result
+                        .speedup
+                        .map_or("none".to_string(), |s| format!("{:.1}x", s.ratio)),
+                })
+            }));
+
+            let ratios: Vec<f64> = cells
+                .iter()
+                .flatten()
+                .filter(|result| !result.is_baseline && timed(result))
+                .filter_map(|result| result.speedup.map(|s| s.ratio))
+                .collect();
+            let spread = match (
+                ratios.iter().copied().reduce(f64::max),
+                ratios.iter().copied().reduce(f64::min),
+            ) {
+                (Some(best), Some(worst)) if ratios.len() > 1 && worst > 0.0 => {
+                    format!("{:.2}x", best / worst)
+                }
+                _ => "-----".to_string(),
+            };
+            table_row.push(Cell::new(&spread));
+            table.add_row(Row::new(table_row));
+        }
+
+        table.printstd();
+    }
+    Ok(())
+}
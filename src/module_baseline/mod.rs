@@ -1,53 +1,77 @@
-use crate::common::AICodeGenStatus;
-use crate::common::CandidateInfo;
-use crate::common::CandidateInfo2;
-use chrono::NaiveDate;
+use crate::common::{CandidateInfo, candidate};
+use crate::registry::register;
+use crate::task::StringDistance;
+use crate::task::U64Function;
 use std::vec;
 
 // Note: this functions was hand-crafted and cannot change.
 // They represent the baseline that each AI has to improve upon.
 
-// add to the vectors as more attempts an this function are made by the AI
-pub fn get_candidates() -> CandidateInfo {
+register!(StringDistance => get_candidates, U64Function => get_candidates2);
+
+// add to the vector as more attempts an this function are made by the AI
+pub fn get_candidates() -> CandidateInfo<StringDistance> {
     CandidateInfo::new(
-        String::from("Baseline"),
-        vec!["levenshstein distance".to_string()],
-        vec![NaiveDate::from_ymd_opt(2025, 1, 2).unwrap()],
-        vec![AICodeGenStatus::Ok],
-        vec![levenshtein_distance],
+        "Baseline",
+        vec![
+            candidate!(
+                StringDistance,
+                "levenshstein distance",
+                levenshtein_distance,
+                "2025-01-02",
+                Ok
+            ),
+            candidate!(
+                StringDistance,
+                "levenshtein distance (chars)",
+                levenshtein_distance_chars,
+                "2026-10-18",
+                Ok
+            ),
+            candidate!(
+                StringDistance,
+                "levenshtein distance (graphemes)",
+                levenshtein_distance_graphemes,
+                "2026-10-18",
+                Ok
+            ),
+        ],
     )
 }
 
-pub fn get_candidates2() -> CandidateInfo2 {
-    CandidateInfo2::new(
-        String::from("Baseline"),
-        vec![
-            "sum of divisors".to_string(),
-            "count of primes".to_string(),
-            "fibonacci".to_string(),
-            "highly composite".to_string(),
-            "sum of proper divisors".to_string(),
-        ],
-        vec![
-            NaiveDate::from_ymd_opt(2025, 2, 3).unwrap(),
-            NaiveDate::from_ymd_opt(2025, 2, 3).unwrap(),
-            NaiveDate::from_ymd_opt(2025, 2, 3).unwrap(),
-            NaiveDate::from_ymd_opt(2025, 2, 3).unwrap(),
-            NaiveDate::from_ymd_opt(2025, 2, 3).unwrap(),
-        ],
-        vec![
-            AICodeGenStatus::Ok,
-            AICodeGenStatus::Ok,
-            AICodeGenStatus::Ok,
-            AICodeGenStatus::Ok,
-            AICodeGenStatus::Ok,
-        ],
+pub fn get_candidates2() -> CandidateInfo<U64Function> {
+    CandidateInfo::new(
+        "Baseline",
         vec![
-            sum_of_divisors,
-            count_primes,
-            fibonacci,
-            highly_composite,
-            sum_of_proper_divisors,
+            candidate!(
+                U64Function,
+                "sum of divisors",
+                sum_of_divisors,
+                "2025-02-03",
+                Ok
+            ),
+            candidate!(
+                U64Function,
+                "count of primes",
+                count_primes,
+                "2025-02-03",
+                Ok
+            ),
+            candidate!(U64Function, "fibonacci", fibonacci, "2025-02-03", Ok),
+            candidate!(
+                U64Function,
+                "highly composite",
+                highly_composite,
+                "2025-02-03",
+                Ok
+            ),
+            candidate!(
+                U64Function,
+                "sum of proper divisors",
+                sum_of_proper_divisors,
+                "2025-02-03",
+                Ok
+            ),
         ],
     )
 }
@@ -94,6 +118,69 @@ pub fn levenshtein_distance(s: &str, t: &str) -> usize {
     matrix[m][n]
 }
 
+/// Levenshtein distance counted in Unicode scalar values (chars): "é" and "文"
+/// are one character each, and "e\u{301}" (e and a combining accent) is two.
+#[inline(never)]
+#[allow(clippy::needless_range_loop)]
+// You are a highly skilled Rust developer. Your task is to optimize the following Rust function for maximum speed and efficiency. The code will run on a x86-x64 system with an AMD Ryzen 7 processor. Readability of the code is not important. Please provide the optimized code. Here is the function that needs optimization:
+pub fn levenshtein_distance_chars(s: &str, t: &str) -> usize {
+    let s: Vec<char> = s.chars().collect();
+    let t: Vec<char> = t.chars().collect();
+    let (m, n) = (s.len(), t.len());
+
+    let mut matrix = vec![vec![0; n + 1]; m + 1];
+    for i in 0..=m {
+        matrix[i][0] = i;
+    }
+    for j in 0..=n {
+        matrix[0][j] = j;
+    }
+
+    for i in 1..=m {
+        for j in 1..=n {
+            let cost = if s[i - 1] == t[j - 1] { 0 } else { 1 };
+            matrix[i][j] = (matrix[i - 1][j] + 1) // Deletion
+                .min(matrix[i][j - 1] + 1) // Insertion
+                .min(matrix[i - 1][j - 1] + cost); // Substitution
+        }
+    }
+
+    matrix[m][n]
+}
+
+/// Levenshtein distance counted in extended grapheme clusters, the characters
+/// a reader sees: "e\u{301}" and "é" are both one, and equal only if written
+/// the same way, as Unicode normalization is not applied.
+#[inline(never)]
+#[allow(clippy::needless_range_loop)]
+// You are a highly skilled Rust developer. Your task is to optimize the following Rust function for maximum speed and efficiency. The code will run on a x86-x64 system with an AMD Ryzen 7 processor. Readability of the code is not important. Please provide the optimized code. Here is the function that needs optimization:
+pub fn levenshtein_distance_graphemes(s: &str, t: &str) -> usize {
+    use unicode_segmentation::UnicodeSegmentation;
+
+    let s: Vec<&str> = s.graphemes(true).collect();
+    let t: Vec<&str> = t.graphemes(true).collect();
+    let (m, n) = (s.len(), t.len());
+
+    let mut matrix = vec![vec![0; n + 1]; m + 1];
+    for i in 0..=m {
+        matrix[i][0] = i;
+    }
+    for j in 0..=n {
+        matrix[0][j] = j;
+    }
+
+    for i in 1..=m {
+        for j in 1..=n {
+            let cost = if s[i - 1] == t[j - 1] { 0 } else { 1 };
+            matrix[i][j] = (matrix[i - 1][j] + 1) // Deletion
+                .min(matrix[i][j - 1] + 1) // Insertion
+                .min(matrix[i - 1][j - 1] + cost); // Substitution
+        }
+    }
+
+    matrix[m][n]
+}
+
 /// Returns the sum of all divisors of the given number `n`.
 /// Naive implementation iterates up to `n`.
 #[inline(never)]
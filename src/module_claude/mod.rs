@@ -1,52 +1,67 @@
-use crate::common::AICodeGenStatus;
-use crate::common::CandidateInfo;
-use crate::common::CandidateInfo2;
-use chrono::NaiveDate;
+use crate::common::{CandidateInfo, candidate};
+use crate::registry::register;
+use crate::task::StringDistance;
+use crate::task::U64Function;
 
 // found here: https://claude.ai/
 // version can be found by asking claude "what version are you?"
 
-// add to the vectors as more attempts an this function are made by the AI
-pub fn get_candidates() -> CandidateInfo {
+register!(StringDistance => get_candidates, U64Function => get_candidates2);
+
+// add to the vector as more attempts an this function are made by the AI
+pub fn get_candidates() -> CandidateInfo<StringDistance> {
     CandidateInfo::new(
-        String::from("claude 3.7 Sonnet"),
-        vec!["levenshstein distance".to_string()],
-        vec![NaiveDate::from_ymd_opt(2025, 3, 3).unwrap()],
-        vec![AICodeGenStatus::Ok],
-        vec![levenshtein_distance],
+        "claude 3.7 Sonnet",
+        vec![candidate!(
+            StringDistance,
+            "levenshstein distance",
+            levenshtein_distance,
+            "2025-03-03",
+            Ok
+        )],
     )
 }
 
-pub fn get_candidates2() -> CandidateInfo2 {
-    CandidateInfo2::new(
-        String::from("claude 3.7 Sonnet"),
-        vec![
-            "sum of divisors".to_string(),
-            "count of primes".to_string(),
-            "fibonacci".to_string(),
-            "highly composite".to_string(),
-            "sum of proper divisors".to_string(),
-        ],
-        vec![
-            NaiveDate::from_ymd_opt(2025, 3, 3).unwrap(),
-            NaiveDate::from_ymd_opt(2025, 3, 3).unwrap(),
-            NaiveDate::from_ymd_opt(2025, 3, 3).unwrap(),
-            NaiveDate::from_ymd_opt(2025, 3, 3).unwrap(),
-            NaiveDate::from_ymd_opt(2025, 3, 3).unwrap(),
-        ],
-        vec![
-            AICodeGenStatus::Ok,
-            AICodeGenStatus::Ok,
-            AICodeGenStatus::IncorrectResult,
-            AICodeGenStatus::Ok,
-            AICodeGenStatus::Ok,
-        ],
+pub fn get_candidates2() -> CandidateInfo<U64Function> {
+    CandidateInfo::new(
+        "claude 3.7 Sonnet",
         vec![
-            sum_of_divisors,
-            count_primes,
-            fibonacci,
-            highly_composite,
-            sum_of_proper_divisors,
+            candidate!(
+                U64Function,
+                "sum of divisors",
+                sum_of_divisors,
+                "2025-03-03",
+                Ok
+            ),
+            candidate!(
+                U64Function,
+                "count of primes",
+                count_primes,
+                "2025-03-03",
+                Ok
+            ),
+            candidate!(
+                U64Function,
+                "fibonacci",
+                fibonacci,
+                "2025-03-03",
+                IncorrectResult
+            )
+            .attempt(2),
+            candidate!(
+                U64Function,
+                "highly composite",
+                highly_composite,
+                "2025-03-03",
+                Ok
+            ),
+            candidate!(
+                U64Function,
+                "sum of proper divisors",
+                sum_of_proper_divisors,
+                "2025-03-03",
+                Ok
+            ),
         ],
     )
 }
@@ -1,8 +1,7 @@
-use chrono::NaiveDate;
-
-use crate::common::AICodeGenStatus;
-use crate::common::CandidateInfo;
-use crate::common::CandidateInfo2;
+use crate::common::{CandidateInfo, candidate};
+use crate::registry::register;
+use crate::task::StringDistance;
+use crate::task::U64Function;
 
 // found here: https://copilot.microsoft.com/
 // Microsoft Copilot does not show version number. The only way to know
@@ -11,63 +10,71 @@ use crate::common::CandidateInfo2;
 // I'll assume, without any other information, that any time there are
 // new release notes, that the AI has been updated.
 
-// add to the vectors as more attempts an this function are made by the AI
-pub fn get_candidates() -> CandidateInfo {
+register!(StringDistance => get_candidates, U64Function => get_candidates2);
+
+// add to the vector as more attempts an this function are made by the AI
+pub fn get_candidates() -> CandidateInfo<StringDistance> {
     CandidateInfo::new(
-        String::from("Microsoft Copilot"),
-        vec![
-            "levenshstein distance".to_string(),
-            "levenshstein distance".to_string(),
-            "levenshstein distance".to_string(),
-        ],
-        vec![
-            NaiveDate::from_ymd_opt(2025, 1, 2).unwrap(),
-            NaiveDate::from_ymd_opt(2025, 1, 13).unwrap(),
-            NaiveDate::from_ymd_opt(2025, 1, 25).unwrap(),
-        ],
-        vec![
-            AICodeGenStatus::Ok,
-            AICodeGenStatus::Ok,
-            AICodeGenStatus::Ok,
-        ],
+        "Microsoft Copilot",
         vec![
-            levenshtein_distance,
-            levenshtein_distance2,
-            levenshtein_distance3,
+            candidate!(
+                StringDistance,
+                "levenshstein distance",
+                levenshtein_distance,
+                "2025-01-02",
+                Ok
+            ),
+            candidate!(
+                StringDistance,
+                "levenshstein distance",
+                levenshtein_distance2,
+                "2025-01-13",
+                Ok
+            ),
+            candidate!(
+                StringDistance,
+                "levenshstein distance",
+                levenshtein_distance3,
+                "2025-01-25",
+                Ok
+            ),
         ],
     )
 }
 
-pub fn get_candidates2() -> CandidateInfo2 {
-    CandidateInfo2::new(
-        String::from("Microsoft Copilot"),
-        vec![
-            "sum of divisors".to_string(),
-            "count of primes".to_string(),
-            "fibonacci".to_string(),
-            "highly composite".to_string(),
-            "sum of proper divisors".to_string(),
-        ],
-        vec![
-            NaiveDate::from_ymd_opt(2025, 3, 3).unwrap(),
-            NaiveDate::from_ymd_opt(2025, 2, 3).unwrap(),
-            NaiveDate::from_ymd_opt(2025, 3, 4).unwrap(),
-            NaiveDate::from_ymd_opt(2025, 2, 4).unwrap(),
-            NaiveDate::from_ymd_opt(2025, 3, 4).unwrap(),
-        ],
-        vec![
-            AICodeGenStatus::Ok,
-            AICodeGenStatus::Ok,
-            AICodeGenStatus::Ok,
-            AICodeGenStatus::Ok,
-            AICodeGenStatus::Ok,
-        ],
+pub fn get_candidates2() -> CandidateInfo<U64Function> {
+    CandidateInfo::new(
+        "Microsoft Copilot",
         vec![
-            sum_of_divisors,
-            highly_composite,
-            fibonacci,
-            count_primes,
-            sum_of_proper_divisors,
+            candidate!(
+                U64Function,
+                "sum of divisors",
+                sum_of_divisors,
+                "2025-03-03",
+                Ok
+            ),
+            candidate!(
+                U64Function,
+                "count of primes",
+                count_primes,
+                "2025-02-03",
+                Ok
+            ),
+            candidate!(U64Function, "fibonacci", fibonacci, "2025-03-04", Ok),
+            candidate!(
+                U64Function,
+                "highly composite",
+                highly_composite,
+                "2025-02-04",
+                Ok
+            ),
+            candidate!(
+                U64Function,
+                "sum of proper divisors",
+                sum_of_proper_divisors,
+                "2025-03-04",
+                Ok
+            ),
         ],
     )
 }
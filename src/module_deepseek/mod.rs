@@ -1,7 +1,7 @@
-use crate::common::AICodeGenStatus;
-use crate::common::CandidateInfo;
-use crate::common::CandidateInfo2;
-use chrono::NaiveDate;
+use crate::common::{CandidateInfo, candidate};
+use crate::registry::register;
+use crate::task::StringDistance;
+use crate::task::U64Function;
 
 // found here: https://chat.deepseek.com/
 // choose the "R1" version
@@ -9,47 +9,63 @@ use chrono::NaiveDate;
 // Note: this functions was hand-crafted and cannot change.
 // They represent the baseline that each AI has to improve upon.
 
-// add to the vectors as more attempts an this function are made by the AI
-pub fn get_candidates() -> CandidateInfo {
+register!(StringDistance => get_candidates, U64Function => get_candidates2);
+
+// add to the vector as more attempts an this function are made by the AI
+pub fn get_candidates() -> CandidateInfo<StringDistance> {
     CandidateInfo::new(
-        String::from("deepseek R1"),
-        vec!["levenshstein distance".to_string()],
-        vec![NaiveDate::from_ymd_opt(2025, 1, 25).unwrap()],
-        vec![AICodeGenStatus::Ok],
-        vec![levenshtein_distance],
+        "deepseek R1",
+        vec![
+            candidate!(
+                StringDistance,
+                "levenshstein distance",
+                levenshtein_distance,
+                "2025-01-25",
+                Ok
+            )
+            .model_version("R1"),
+        ],
     )
 }
 
-pub fn get_candidates2() -> CandidateInfo2 {
-    CandidateInfo2::new(
-        String::from("deepseek R1"),
-        vec![
-            "sum of divisors".to_string(),
-            "count of primes".to_string(),
-            "fibonacci".to_string(),
-            "highly composite".to_string(),
-            "sum of proper divisors".to_string(),
-        ],
-        vec![
-            NaiveDate::from_ymd_opt(2025, 2, 3).unwrap(),
-            NaiveDate::from_ymd_opt(2025, 2, 3).unwrap(),
-            NaiveDate::from_ymd_opt(2025, 2, 3).unwrap(),
-            NaiveDate::from_ymd_opt(2025, 2, 3).unwrap(),
-            NaiveDate::from_ymd_opt(2025, 2, 3).unwrap(),
-        ],
-        vec![
-            AICodeGenStatus::SecondTryOk,
-            AICodeGenStatus::Ok,
-            AICodeGenStatus::Ok,
-            AICodeGenStatus::Ok,
-            AICodeGenStatus::Ok,
-        ],
+pub fn get_candidates2() -> CandidateInfo<U64Function> {
+    CandidateInfo::new(
+        "deepseek R1",
         vec![
-            sum_of_divisors,
-            count_primes,
-            fibonacci,
-            highly_composite,
-            sum_of_proper_divisors,
+            candidate!(
+                U64Function,
+                "sum of divisors",
+                sum_of_divisors,
+                "2025-02-03",
+                SecondTryOk
+            )
+            .model_version("R1"),
+            candidate!(
+                U64Function,
+                "count of primes",
+                count_primes,
+                "2025-02-03",
+                Ok
+            )
+            .model_version("R1"),
+            candidate!(U64Function, "fibonacci", fibonacci, "2025-02-03", Ok).model_version("R1"),
+            candidate!(
+                U64Function,
+                "highly composite",
+                highly_composite,
+                "2025-02-03",
+                Ok
+            )
+            .model_version("R1")
+            .notes("a stub returning 0, marked IncorrectResult in a comment"),
+            candidate!(
+                U64Function,
+                "sum of proper divisors",
+                sum_of_proper_divisors,
+                "2025-02-03",
+                Ok
+            )
+            .model_version("R1"),
         ],
     )
 }
@@ -1,40 +1,37 @@
-use chrono::NaiveDate;
-
-use crate::common::AICodeGenStatus;
-use crate::common::CandidateInfo;
-use crate::common::CandidateInfo2;
+use crate::common::{CandidateInfo, candidate};
+use crate::registry::register;
+use crate::task::StringDistance;
+use crate::task::U64Function;
 
 // found here: https://gemini.google.com/
 // version is listed on page and can the selected
 
-// add to the vectors as more attempts an this function are made by the AI
-pub fn get_candidates() -> CandidateInfo {
+register!(StringDistance => get_candidates, U64Function => get_candidates2);
+
+// add to the vector as more attempts an this function are made by the AI
+pub fn get_candidates() -> CandidateInfo<StringDistance> {
     CandidateInfo::new(
-        String::from("gemini 2.0 Flash"),
-        vec!["levenshstein distance".to_string()],
-        vec![NaiveDate::from_ymd_opt(2025, 1, 2).unwrap()],
-        vec![AICodeGenStatus::Ok],
-        vec![levenshtein_distance],
+        "gemini 2.0 Flash",
+        vec![candidate!(
+            StringDistance,
+            "levenshstein distance",
+            levenshtein_distance,
+            "2025-01-02",
+            Ok
+        )],
     )
 }
 
-pub fn get_candidates2() -> CandidateInfo2 {
-    CandidateInfo2::new(
-        String::from("gemini 2.5 Pro"),
-        vec![
-            "sum of divisors".to_string(),
-        ],
-        vec![
-            NaiveDate::from_ymd_opt(2025, 4, 1).unwrap(),
-
-        ],
-        vec![
-            AICodeGenStatus::Ok,
-        ],
-        vec![
+pub fn get_candidates2() -> CandidateInfo<U64Function> {
+    CandidateInfo::new(
+        "gemini 2.5 Pro",
+        vec![candidate!(
+            U64Function,
+            "sum of divisors",
             sum_of_divisors,
-
-        ],
+            "2025-04-01",
+            Ok
+        )],
     )
 }
 
@@ -1,19 +1,23 @@
-use chrono::NaiveDate;
-
-use crate::common::AICodeGenStatus;
-use crate::common::CandidateInfo;
+use crate::common::{CandidateInfo, candidate};
+use crate::registry::register;
+use crate::task::StringDistance;
 
 // found here: https://x.ai/grok
 // version is displayed at top of page
 
-// add to the vectors as more attempts an this function are made by the AI
-pub fn get_candidates() -> CandidateInfo {
+register!(StringDistance => get_candidates);
+
+// add to the vector as more attempts an this function are made by the AI
+pub fn get_candidates() -> CandidateInfo<StringDistance> {
     CandidateInfo::new(
-        String::from("Grock 2"),
-        vec!["levenshstein distance".to_string()],
-        vec![NaiveDate::from_ymd_opt(2025, 1, 8).unwrap()],
-        vec![AICodeGenStatus::Ok],
-        vec![levenshtein_distance],
+        "Grock 2",
+        vec![candidate!(
+            StringDistance,
+            "levenshstein distance",
+            levenshtein_distance,
+            "2025-01-08",
+            Ok
+        )],
     )
 }
 
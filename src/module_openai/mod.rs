@@ -1,26 +1,33 @@
-use chrono::NaiveDate;
-
-use crate::common::AICodeGenStatus;
-use crate::common::CandidateInfo;
-use crate::common::CandidateInfo2;
+use crate::common::{CandidateInfo, candidate};
+use crate::registry::register;
+use crate::task::StringDistance;
+use crate::task::U64Function;
 
 // found here: https://chatgpt.com/
 // version can be found prompting: "what version are you?"
 
-// add to the vectors as more attempts an this function are made by the AI
-pub fn get_candidates() -> CandidateInfo {
+register!(StringDistance => get_candidates, U64Function => get_candidates2);
+
+// add to the vector as more attempts an this function are made by the AI
+pub fn get_candidates() -> CandidateInfo<StringDistance> {
     CandidateInfo::new(
-        String::from("ChatGPT, version 2"),
+        "ChatGPT, version 2",
         vec![
-            "levenshstein distance".to_string(),
-            "levenshstein distance".to_string(),
+            candidate!(
+                StringDistance,
+                "levenshstein distance",
+                levenshtein_distance,
+                "2025-01-02",
+                Ok
+            ),
+            candidate!(
+                StringDistance,
+                "levenshstein distance",
+                levenshtein_distance2,
+                "2025-01-25",
+                Ok
+            ),
         ],
-        vec![
-            NaiveDate::from_ymd_opt(2025, 1, 2).unwrap(),
-            NaiveDate::from_ymd_opt(2025, 1, 25).unwrap(),
-        ],
-        vec![AICodeGenStatus::Ok, AICodeGenStatus::Ok],
-        vec![levenshtein_distance, levenshtein_distance2],
     )
 }
 
@@ -29,13 +36,16 @@ pub fn get_candidates() -> CandidateInfo {
 //"fibonacci".to_string(),
 //"highly composite".to_string(),
 //"sum of proper divisors".to_string(),
-pub fn get_candidates2() -> CandidateInfo2 {
-    CandidateInfo2::new(
-        String::from("ChatGPT, version 2"),
-        vec!["sum of divisors".to_string()],
-        vec![NaiveDate::from_ymd_opt(2025, 2, 3).unwrap()],
-        vec![AICodeGenStatus::Ok],
-        vec![sum_of_divisors],
+pub fn get_candidates2() -> CandidateInfo<U64Function> {
+    CandidateInfo::new(
+        "ChatGPT, version 2",
+        vec![candidate!(
+            U64Function,
+            "sum of divisors",
+            sum_of_divisors,
+            "2025-02-03",
+            Ok
+        )],
     )
 }
 
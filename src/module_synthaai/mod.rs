@@ -1,19 +1,23 @@
-use chrono::NaiveDate;
-
-use crate::common::AICodeGenStatus;
-use crate::common::CandidateInfo;
+use crate::common::{CandidateInfo, candidate};
+use crate::registry::register;
+use crate::task::StringDistance;
 
 // found here: https://syntha.ai/optimizers/rust
 // I couldn't find a way to get the name or version of the AI engine used
 
-// add to the vectors as more attempts an this function are made by the AI
-pub fn get_candidates() -> CandidateInfo {
+register!(StringDistance => get_candidates);
+
+// add to the vector as more attempts an this function are made by the AI
+pub fn get_candidates() -> CandidateInfo<StringDistance> {
     CandidateInfo::new(
-        String::from("syntha.ai"),
-        vec!["levenshstein distance".to_string()],
-        vec![NaiveDate::from_ymd_opt(2025, 1, 2).unwrap()],
-        vec![AICodeGenStatus::Ok],
-        vec![levenshtein_distance],
+        "syntha.ai",
+        vec![candidate!(
+            StringDistance,
+            "levenshstein distance",
+            levenshtein_distance,
+            "2025-01-02",
+            Ok
+        )],
     )
 }
 
@@ -1,19 +1,23 @@
-use chrono::NaiveDate;
-
-use crate::common::AICodeGenStatus;
-use crate::common::CandidateInfo;
+use crate::common::{CandidateInfo, candidate};
+use crate::registry::register;
+use crate::task::StringDistance;
 
 // found here: https://www.ibm.com/watsonx
 // requires an account and doesn't work very well so will likely not try it again.
 
-// add to the vectors as more attempts an this function are made by the AI
-pub fn get_candidates() -> CandidateInfo {
+register!(StringDistance => get_candidates);
+
+// add to the vector as more attempts an this function are made by the AI
+pub fn get_candidates() -> CandidateInfo<StringDistance> {
     CandidateInfo::new(
-        String::from("watson"),
-        vec!["levenshstein distance".to_string()],
-        vec![NaiveDate::from_ymd_opt(2025, 1, 10).unwrap()],
-        vec![AICodeGenStatus::AIRefusedToAnswer],
-        vec![levenshtein_distance],
+        "watson",
+        vec![candidate!(
+            StringDistance,
+            "levenshstein distance",
+            levenshtein_distance,
+            "2025-01-10",
+            AIRefusedToAnswer
+        )],
     )
 }
 
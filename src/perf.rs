@@ -0,0 +1,252 @@
+use serde::{Deserialize, Serialize};
+
+// Iterations per second say which candidate is faster, not why. With
+// --counters the child also runs the candidate for a short while under the
+// CPU's performance counters (perf_event_open, so Linux only) and reports
+// cycles, instructions, branch misses and cache misses per call. Each counter
+// is opened on its own, so a CPU or VM that lacks one (L1d misses often) only
+// loses that column; where perf_event_open is not allowed at all, as in most
+// containers, every column is empty and a warning says why. The counted calls
+// are separate from the timed ones, so counting costs nothing in Iter/Sec.
+
+/// Per-call averages of the hardware counters; None for a counter that could
+/// not be opened.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Counters {
+    pub cycles: Option<f64>,
+    pub instructions: Option<f64>,
+    pub branch_misses: Option<f64>,
+    pub l1d_misses: Option<f64>,
+    pub llc_misses: Option<f64>,
+}
+
+impl Counters {
+    /// Instructions per cycle.
+    pub fn ipc(&self) -> Option<f64> {
+        match (self.instructions, self.cycles) {
+            (Some(instructions), Some(cycles)) if cycles > 0.0 => Some(instructions / cycles),
+            _ => None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Counters::default()
+    }
+
+    /// The counters as space-separated fields, "-" for a missing one, for the
+    /// child's output.
+    pub fn fields(self) -> String {
+        self.values()
+            .into_iter()
+            .map(|value| value.map_or("-".to_string(), |value| value.to_string()))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    pub fn parse(fields: &str) -> Option<Counters> {
+        let values = fields
+            .split(' ')
+            .map(|field| match field {
+                "-" => Ok(None),
+                _ => field.parse().map(Some),
+            })
+            .collect::<Result<Vec<Option<f64>>, _>>()
+            .ok()?;
+        let [cycles, instructions, branch_misses, l1d_misses, llc_misses] = values[..] else {
+            return None;
+        };
+        Some(Counters {
+            cycles,
+            instructions,
+            branch_misses,
+            l1d_misses,
+            llc_misses,
+        })
+    }
+
+    fn values(&self) -> [Option<f64>; 5] {
+        [
+            self.cycles,
+            self.instructions,
+            self.branch_misses,
+            self.l1d_misses,
+            self.llc_misses,
+        ]
+    }
+}
+
+/// Formats a per-call count for a table cell.
+pub fn format_count(value: Option<f64>) -> String {
+    value.map_or("-".to_string(), |value| format!("{:.1}", value))
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::fs::File;
+    use std::io::{self, Read};
+    use std::os::fd::FromRawFd;
+
+    const PERF_TYPE_HARDWARE: u32 = 0;
+    const PERF_TYPE_HW_CACHE: u32 = 3;
+
+    const PERF_COUNT_HW_CPU_CYCLES: u64 = 0;
+    const PERF_COUNT_HW_INSTRUCTIONS: u64 = 1;
+    const PERF_COUNT_HW_CACHE_MISSES: u64 = 3;
+    const PERF_COUNT_HW_BRANCH_MISSES: u64 = 5;
+    /// L1d (0), read (0 << 8), miss (1 << 16).
+    const PERF_COUNT_HW_CACHE_L1D_READ_MISS: u64 = 1 << 16;
+
+    const PERF_FORMAT_TOTAL_TIME_ENABLED: u64 = 1;
+    const PERF_FORMAT_TOTAL_TIME_RUNNING: u64 = 2;
+
+    const FLAG_DISABLED: u64 = 1;
+    const FLAG_EXCLUDE_KERNEL: u64 = 1 << 5;
+    const FLAG_EXCLUDE_HV: u64 = 1 << 6;
+
+    const PERF_EVENT_IOC_ENABLE: libc::c_ulong = 0x2400;
+    const PERF_EVENT_IOC_DISABLE: libc::c_ulong = 0x2401;
+
+    /// The first version of struct perf_event_attr, which every kernel accepts.
+    #[repr(C)]
+    #[derive(Default)]
+    struct PerfEventAttr {
+        type_: u32,
+        size: u32,
+        config: u64,
+        sample_period: u64,
+        sample_type: u64,
+        read_format: u64,
+        flags: u64,
+        wakeup_events: u32,
+        bp_type: u32,
+        config1: u64,
+    }
+
+    pub const EVENTS: [(u32, u64); 5] = [
+        (PERF_TYPE_HARDWARE, PERF_COUNT_HW_CPU_CYCLES),
+        (PERF_TYPE_HARDWARE, PERF_COUNT_HW_INSTRUCTIONS),
+        (PERF_TYPE_HARDWARE, PERF_COUNT_HW_BRANCH_MISSES),
+        (PERF_TYPE_HW_CACHE, PERF_COUNT_HW_CACHE_L1D_READ_MISS),
+        (PERF_TYPE_HARDWARE, PERF_COUNT_HW_CACHE_MISSES),
+    ];
+
+    /// One counter of this thread's user-space events, created disabled.
+    pub struct Counter {
+        file: File,
+    }
+
+    impl Counter {
+        pub fn open((type_, config): (u32, u64)) -> io::Result<Counter> {
+            let attr = PerfEventAttr {
+                type_,
+                size: size_of::<PerfEventAttr>() as u32,
+                config,
+                read_format: PERF_FORMAT_TOTAL_TIME_ENABLED | PERF_FORMAT_TOTAL_TIME_RUNNING,
+                flags: FLAG_DISABLED | FLAG_EXCLUDE_KERNEL | FLAG_EXCLUDE_HV,
+                ..PerfEventAttr::default()
+            };
+            // this thread, any CPU, no group, no flags
+            let fd = unsafe {
+                libc::syscall(
+                    libc::SYS_perf_event_open,
+                    &attr as *const PerfEventAttr,
+                    0,
+                    -1,
+                    -1,
+                    0,
+                )
+            };
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Counter {
+                file: unsafe { File::from_raw_fd(fd as i32) },
+            })
+        }
+
+        pub fn enable(&self) {
+            self.ioctl(PERF_EVENT_IOC_ENABLE);
+        }
+
+        pub fn disable(&self) {
+            self.ioctl(PERF_EVENT_IOC_DISABLE);
+        }
+
+        /// The count, scaled up for the time the kernel had the counter
+        /// multiplexed out.
+        pub fn read(&mut self) -> Option<f64> {
+            let mut buffer = [0u8; 24];
+            self.file.read_exact(&mut buffer).ok()?;
+            let field = |i: usize| u64::from_ne_bytes(buffer[i * 8..i * 8 + 8].try_into().unwrap());
+            let (value, enabled, running) = (field(0), field(1), field(2));
+            if running == 0 {
+                return None;
+            }
+            Some(value as f64 * enabled as f64 / running as f64)
+        }
+
+        fn ioctl(&self, request: libc::c_ulong) {
+            use std::os::fd::AsRawFd;
+            unsafe {
+                libc::ioctl(self.file.as_raw_fd(), request as _, 0);
+            }
+        }
+    }
+}
+
+/// Whether the counters can be used here; the error says why not.
+#[cfg(target_os = "linux")]
+pub fn probe() -> Result<(), String> {
+    linux::Counter::open(linux::EVENTS[0])
+        .map(|_| ())
+        .map_err(|e| {
+            format!(
+                "perf_event_open: {} (see /proc/sys/kernel/perf_event_paranoid)",
+                e
+            )
+        })
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn probe() -> Result<(), String> {
+    Err("hardware counters are only read on Linux".to_string())
+}
+
+/// Runs `f` `calls` times under every counter that can be opened and returns
+/// the counts per call.
+#[cfg(target_os = "linux")]
+pub fn count<F: FnMut()>(mut f: F, calls: u64) -> Counters {
+    let mut counters: Vec<Option<linux::Counter>> = linux::EVENTS
+        .iter()
+        .map(|event| linux::Counter::open(*event).ok())
+        .collect();
+
+    for counter in counters.iter().flatten() {
+        counter.enable();
+    }
+    for _ in 0..calls {
+        f();
+    }
+    for counter in counters.iter().flatten() {
+        counter.disable();
+    }
+
+    let mut values = counters.iter_mut().map(|counter| {
+        counter
+            .as_mut()
+            .and_then(|counter| counter.read())
+            .map(|value| value / calls as f64)
+    });
+    Counters {
+        cycles: values.next().flatten(),
+        instructions: values.next().flatten(),
+        branch_misses: values.next().flatten(),
+        l1d_misses: values.next().flatten(),
+        llc_misses: values.next().flatten(),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn count<F: FnMut()>(_f: F, _calls: u64) -> Counters {
+    Counters::default()
+}
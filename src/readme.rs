@@ -0,0 +1,67 @@
+use std::fs;
+use std::path::Path;
+
+use crate::cli::Format;
+use crate::common;
+use crate::common::ResultRow;
+use crate::export::Report;
+
+// The results section of the README is generated: everything between the two
+// markers is replaced with one Markdown table per task, sorted the same way as
+// the tables printed by `run`.
+
+pub const BEGIN_MARKER: &str = "<!-- BEGIN RESULTS: generated by `update-readme`, do not edit -->";
+pub const END_MARKER: &str = "<!-- END RESULTS -->";
+
+/// Replaces the results section of the README at `path` with the tables of `report`.
+pub fn update(path: &Path, report: &Report) -> Result<(), String> {
+    let readme = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    let (Some(begin), Some(end)) = (readme.find(BEGIN_MARKER), readme.find(END_MARKER)) else {
+        return Err(format!(
+            "{}: missing the markers {} and {}",
+            path.display(),
+            BEGIN_MARKER,
+            END_MARKER
+        ));
+    };
+    if end < begin {
+        return Err(format!(
+            "{}: end marker before begin marker",
+            path.display()
+        ));
+    }
+
+    let updated = format!(
+        "{}{}\n\n{}\n\n{}",
+        &readme[..begin],
+        BEGIN_MARKER,
+        render(report)?.trim_end(),
+        &readme[end..]
+    );
+    fs::write(path, updated).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn render(report: &Report) -> Result<String, String> {
+    let rows = report
+        .results
+        .iter()
+        .map(ResultRow::try_from)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut markdown = format!(
+        "_Generated {} on {} from revision {}, {} s per candidate._\n\n_Build: {}._\n\n",
+        report.run_timestamp,
+        report.host.describe(),
+        report.git_revision,
+        report.duration_sec,
+        report.build.describe()
+    );
+    for (function_name, function_results) in common::sort_results(&rows) {
+        markdown.push_str(&format!("### {}\n\n", function_name));
+        markdown.push_str(&common::results_table(&function_results, Format::Markdown).to_string());
+        markdown.push('\n');
+    }
+
+    Ok(markdown)
+}
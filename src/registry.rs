@@ -0,0 +1,149 @@
+use std::any::Any;
+
+use crate::common::{Candidate, CandidateInfo};
+use crate::task::{self, StringDistance, Task, U64Function};
+
+// Every candidate module registers its get_candidates functions with
+// `register!`, once, and the harness collects them at startup; main.rs only
+// needs the module's `mod` line. A module without candidates for a task family
+// simply does not register that family, and `list` reports the tasks each
+// engine has no attempt at. A family is a `Task` implementation with a
+// `Registered` one saying what it is called and what its tasks are, and an
+// entry in main.rs' FAMILIES.
+
+pub type Getter<T> = fn() -> CandidateInfo<T>;
+
+pub struct Registration {
+    /// Path of the registering module, e.g. AIRustEval::module_grok.
+    pub module: &'static str,
+    /// The module's `Getter` for one family.
+    pub getter: &'static (dyn Any + Send + Sync),
+}
+
+inventory::collect!(Registration);
+
+/// Registers the get_candidates functions of the module, each under the task
+/// family it returns candidates for, e.g.
+/// `register!(StringDistance => get_candidates, U64Function => get_candidates2);`
+macro_rules! register {
+    ($($family:ident => $getter:path),+ $(,)?) => {
+        $(
+            inventory::submit! {
+                $crate::registry::Registration {
+                    module: module_path!(),
+                    getter: &($getter as $crate::registry::Getter<$crate::task::$family>),
+                }
+            }
+        )+
+    };
+}
+pub(crate) use register;
+
+/// Task families whose candidates are registered.
+pub trait Registered: Task + Sized + 'static {
+    /// Name of the family on the command line of the child processes.
+    const FAMILY: &'static str;
+
+    /// Name of the module function returning a module's candidates of the family.
+    const GETTER: &'static str;
+
+    fn tasks() -> Vec<Self>;
+}
+
+impl Registered for StringDistance {
+    const FAMILY: &'static str = "string-distance";
+    const GETTER: &'static str = "get_candidates";
+
+    fn tasks() -> Vec<Self> {
+        task::string_distance_tasks()
+    }
+}
+
+impl Registered for U64Function {
+    const FAMILY: &'static str = "u64";
+    const GETTER: &'static str = "get_candidates2";
+
+    fn tasks() -> Vec<Self> {
+        task::u64_tasks()
+    }
+}
+
+/// The candidates of every module registered for the family: the baseline
+/// first, then the engines by module name, so that the order (which the child
+/// processes rely on) is the same in every run. Fails if a candidate is not
+/// one of `tasks`' or its function is not named after the task's.
+pub fn modules<T: Registered>(tasks: &[T]) -> Result<Vec<CandidateInfo<T>>, String> {
+    let mut registrations: Vec<(&str, Getter<T>)> = inventory::iter::<Registration>
+        .into_iter()
+        .filter_map(|registration| {
+            registration
+                .getter
+                .downcast_ref::<Getter<T>>()
+                .map(|getter| (registration.module, *getter))
+        })
+        .collect();
+    registrations.sort_by_key(|(module, _)| (!is_baseline(module), *module));
+
+    if let Some(window) = registrations.windows(2).find(|w| w[0].0 == w[1].0) {
+        return Err(format!(
+            "{} registers more than one get_candidates function for the same tasks",
+            window[0].0
+        ));
+    }
+    if !registrations
+        .first()
+        .is_some_and(|(module, _)| is_baseline(module))
+    {
+        return Err("module_baseline registers no candidates for these tasks".to_string());
+    }
+
+    let modules: Vec<CandidateInfo<T>> = registrations
+        .into_iter()
+        .map(|(_, getter)| getter())
+        .collect();
+    for module in &modules {
+        for candidate in &module.candidates {
+            check(tasks, &module.engine_name, candidate)?;
+        }
+    }
+    Ok(modules)
+}
+
+/// A candidate's function is the task's baseline function, or one named after
+/// it (levenshtein_distance3, or sum_of_divisors_20250601::fast_sum for a file
+/// written by `ingest`), so that a candidate listed under the wrong task, as
+/// happened with parallel vectors, is caught before anything runs.
+fn check<T: Task>(tasks: &[T], engine_name: &str, candidate: &Candidate<T>) -> Result<(), String> {
+    let task = task::find(tasks, candidate.task).ok_or_else(|| {
+        format!(
+            "{} / {}: not a task with the signature {}",
+            engine_name,
+            candidate.task,
+            T::SIGNATURE
+        )
+    })?;
+    let belongs = candidate
+        .function_name
+        .split("::")
+        .map(|segment| {
+            segment
+                .trim()
+                .trim_end_matches(|c: char| c.is_ascii_digit() || c == '_')
+        })
+        .any(|segment| segment == task.function_name());
+    if belongs {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} / {}: function {} does not belong to this task (expected {})",
+            engine_name,
+            candidate.task,
+            candidate.function_name,
+            task.function_name()
+        ))
+    }
+}
+
+fn is_baseline(module: &str) -> bool {
+    module.ends_with("::module_baseline")
+}
@@ -0,0 +1,447 @@
+use core::fmt;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use crate::clock::Clock;
+
+// Throughput is measured as many short samples instead of one long window so
+// that a noisy moment on the machine only spoils a few samples and the spread
+// of the samples tells us how much to trust the number.
+
+/// Number of timed samples collected after warmup.
+pub const SAMPLE_COUNT: u32 = 30;
+
+/// Samples collected even when they overrun the duration, for a candidate
+/// whose calls take longer than a window.
+pub const MIN_SAMPLES: usize = 3;
+
+/// Fraction of the requested duration spent warming up caches and branch predictors.
+pub const WARMUP_FRACTION: f64 = 0.1;
+
+/// Descriptive statistics over the iterations/second of each sample.
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Summary {
+    pub samples: u32,
+    pub median: f64,
+    pub mean: f64,
+    pub std_dev: f64,
+    pub min: f64,
+    pub ci95_low: f64,
+    pub ci95_high: f64,
+}
+
+impl Summary {
+    pub fn from_samples(samples: &[f64]) -> Self {
+        if samples.is_empty() {
+            return Summary::default();
+        }
+
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(f64::total_cmp);
+
+        let n = sorted.len();
+        let median = if n.is_multiple_of(2) {
+            (sorted[n / 2 - 1] + sorted[n / 2]) / 2.0
+        } else {
+            sorted[n / 2]
+        };
+        let mean = sorted.iter().sum::<f64>() / n as f64;
+        let std_dev = if n > 1 {
+            (sorted.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1) as f64).sqrt()
+        } else {
+            0.0
+        };
+        let half_width = t_critical_95(n) * std_dev / (n as f64).sqrt();
+
+        Summary {
+            samples: n as u32,
+            median,
+            mean,
+            std_dev,
+            min: sorted[0],
+            ci95_low: (mean - half_width).max(0.0),
+            ci95_high: mean + half_width,
+        }
+    }
+
+    /// Speedup of `self` relative to `baseline`: the ratio of the medians, as
+    /// Iter/Sec shows the median, with the range obtained by dividing the
+    /// extremes of the two confidence intervals (widened to include the ratio
+    /// when a skewed median falls outside them).
+    pub fn speedup_over(&self, baseline: &Summary) -> Speedup {
+        if baseline.median <= 0.0 {
+            return Speedup::default();
+        }
+        let ratio = self.median / baseline.median;
+        let high = if baseline.ci95_low > 0.0 {
+            self.ci95_high / baseline.ci95_low
+        } else {
+            f64::INFINITY
+        };
+        Speedup {
+            ratio,
+            low: (self.ci95_low / baseline.ci95_high).min(ratio),
+            high: high.max(ratio),
+        }
+    }
+
+    pub fn ci95_string(&self) -> String {
+        format!("{:.2}-{:.2}", self.ci95_low, self.ci95_high)
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Speedup {
+    pub ratio: f64,
+    pub low: f64,
+    pub high: f64,
+}
+
+impl fmt::Display for Speedup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.1}x ({:.1}-{:.1})", self.ratio, self.low, self.high)
+    }
+}
+
+/// Two-sided 95% critical value of Student's t distribution for `n` samples.
+fn t_critical_95(n: usize) -> f64 {
+    match n {
+        0 | 1 => 0.0,
+        _ => t_critical_95_df((n - 1) as f64),
+    }
+}
+
+/// Two-sided 95% critical value of Student's t distribution for `df` degrees
+/// of freedom, rounded down to a whole number of degrees.
+fn t_critical_95_df(df: f64) -> f64 {
+    const TABLE: [f64; 30] = [
+        12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+        2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+        2.052, 2.048, 2.045, 2.042,
+    ];
+    let df = df.floor().max(1.0) as usize;
+    TABLE.get(df - 1).copied().unwrap_or(1.960)
+}
+
+/// Welch's t-test: true if the means of `a` and `b` differ at the 95% level.
+pub fn differs_significantly(a: &Summary, b: &Summary) -> bool {
+    if a.samples < 2 || b.samples < 2 {
+        return false;
+    }
+    let (na, nb) = (a.samples as f64, b.samples as f64);
+    let (va, vb) = (a.std_dev.powi(2) / na, b.std_dev.powi(2) / nb);
+    if va + vb == 0.0 {
+        return a.mean != b.mean;
+    }
+
+    let t = (a.mean - b.mean).abs() / (va + vb).sqrt();
+    let df = (va + vb).powi(2) / (va.powi(2) / (na - 1.0) + vb.powi(2) / (nb - 1.0));
+    t > t_critical_95_df(df)
+}
+
+/// Fitted power law time = c · size^exponent of a candidate timed at several sizes.
+#[derive(Copy, Clone, Debug)]
+pub struct Fit {
+    pub exponent: f64,
+    /// Coefficient of determination of the fit in log-log space.
+    pub r_squared: f64,
+}
+
+impl fmt::Display for Fit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "n^{:.2} (r² {:.2})", self.exponent, self.r_squared)
+    }
+}
+
+/// Least-squares line through (ln size, ln seconds per call) for `points`
+/// of (size, iterations/second). None with fewer than two distinct sizes.
+pub fn fit_exponent(points: &[(u64, f64)]) -> Option<Fit> {
+    let logs: Vec<(f64, f64)> = points
+        .iter()
+        .filter(|(size, rate)| *size > 0 && *rate > 0.0)
+        .map(|(size, rate)| ((*size as f64).ln(), -rate.ln()))
+        .collect();
+    let n = logs.len() as f64;
+    if logs.len() < 2 {
+        return None;
+    }
+
+    let mean_x = logs.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = logs.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let sxx: f64 = logs.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    let sxy: f64 = logs.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    let syy: f64 = logs.iter().map(|(_, y)| (y - mean_y).powi(2)).sum();
+    if sxx == 0.0 {
+        return None;
+    }
+
+    Some(Fit {
+        exponent: sxy / sxx,
+        // equal times leave rounding noise in syy, not a spread to explain
+        r_squared: if syy < 1e-12 {
+            1.0
+        } else {
+            sxy * sxy / (sxx * syy)
+        },
+    })
+}
+
+/// Runs `f` repeatedly for `duration`: a warmup phase, then `SAMPLE_COUNT`
+/// equal windows each yielding one iterations/second sample. A window holds at
+/// least one call, so when calls are longer than a window the sampling stops
+/// once `duration` is used up, after at least MIN_SAMPLES samples.
+/// Returns the last result of `f` and the summary of the samples.
+pub fn measure<R, F>(mut f: F, duration: Duration) -> (R, Summary)
+where
+    F: FnMut() -> R,
+{
+    let warmup = duration.mul_f64(WARMUP_FRACTION);
+    let window = duration.mul_f64(1.0 - WARMUP_FRACTION) / SAMPLE_COUNT;
+
+    let start_time = Instant::now();
+    let mut result = f();
+    while start_time.elapsed() < warmup {
+        result = f();
+    }
+
+    let mut samples = Vec::with_capacity(SAMPLE_COUNT as usize);
+    for _ in 0..SAMPLE_COUNT {
+        if samples.len() >= MIN_SAMPLES && start_time.elapsed() >= duration {
+            break;
+        }
+        let sample_start = Instant::now();
+        let mut run_count = 0u64;
+        while sample_start.elapsed() < window {
+            result = f();
+            run_count += 1;
+        }
+        samples.push(run_count as f64 / sample_start.elapsed().as_secs_f64());
+    }
+
+    (result, Summary::from_samples(&samples))
+}
+
+/// Like `measure`, but reads `clock` once per batch of calls, sized for the
+/// batch to take well above the clock's own cost, and subtracts the cost of
+/// an empty batch from every batch. Stops early as `measure` does.
+pub fn measure_batched<F: FnMut()>(mut f: F, duration: Duration, clock: &Clock) -> Summary {
+    let warmup = duration.mul_f64(WARMUP_FRACTION);
+    let window = duration.mul_f64(1.0 - WARMUP_FRACTION) / SAMPLE_COUNT;
+
+    let start_time = Instant::now();
+    f();
+    while start_time.elapsed() < warmup {
+        f();
+    }
+    let batch = clock.batch_size(&mut f);
+    let overhead = clock.overhead(batch);
+
+    let mut samples = Vec::with_capacity(SAMPLE_COUNT as usize);
+    for _ in 0..SAMPLE_COUNT {
+        if samples.len() >= MIN_SAMPLES && start_time.elapsed() >= duration {
+            break;
+        }
+        let sample_start = Instant::now();
+        let (mut calls, mut ticks) = (0u64, 0u64);
+        while sample_start.elapsed() < window {
+            ticks += clock.time_batch(&mut f, batch).saturating_sub(overhead);
+            calls += batch;
+        }
+        samples.push(calls as f64 / clock.seconds(ticks.max(1)));
+    }
+
+    Summary::from_samples(&samples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9 * expected.abs().max(1.0),
+            "{} is not {}",
+            actual,
+            expected
+        );
+    }
+
+    fn summary(samples: u32, mean: f64, std_dev: f64) -> Summary {
+        Summary {
+            samples,
+            median: mean,
+            mean,
+            std_dev,
+            min: mean,
+            ci95_low: mean,
+            ci95_high: mean,
+        }
+    }
+
+    #[test]
+    fn from_samples_of_an_even_count() {
+        let s = Summary::from_samples(&[4.0, 1.0, 3.0, 2.0]);
+        assert_eq!(s.samples, 4);
+        assert_close(s.median, 2.5);
+        assert_close(s.mean, 2.5);
+        assert_close(s.min, 1.0);
+        // sample standard deviation, divided by n - 1
+        let std_dev = (5.0f64 / 3.0).sqrt();
+        assert_close(s.std_dev, std_dev);
+        // t for 3 degrees of freedom
+        let half_width = 3.182 * std_dev / 2.0;
+        assert_close(s.ci95_low, 2.5 - half_width);
+        assert_close(s.ci95_high, 2.5 + half_width);
+    }
+
+    #[test]
+    fn from_samples_of_an_odd_count() {
+        let s = Summary::from_samples(&[9.0, 1.0, 2.0]);
+        assert_close(s.median, 2.0);
+        assert_close(s.mean, 4.0);
+        assert_close(s.std_dev, 19.0f64.sqrt());
+    }
+
+    #[test]
+    fn from_samples_of_one_or_none() {
+        let s = Summary::from_samples(&[5.0]);
+        assert_eq!(s.samples, 1);
+        assert_close(s.median, 5.0);
+        assert_close(s.std_dev, 0.0);
+        assert_close(s.ci95_low, 5.0);
+        assert_close(s.ci95_high, 5.0);
+
+        let s = Summary::from_samples(&[]);
+        assert_eq!(s.samples, 0);
+        assert_close(s.median, 0.0);
+    }
+
+    #[test]
+    fn from_samples_keeps_the_interval_above_zero() {
+        let s = Summary::from_samples(&[0.0, 10.0]);
+        assert_close(s.ci95_low, 0.0);
+        assert_close(s.ci95_high, 5.0 + 12.706 * 50.0f64.sqrt() / 2.0f64.sqrt());
+    }
+
+    #[test]
+    fn from_samples_uses_the_normal_value_beyond_the_table() {
+        let samples: Vec<f64> = (0..40).map(|i| (i % 2) as f64).collect();
+        let s = Summary::from_samples(&samples);
+        let std_dev = (10.0f64 / 39.0).sqrt();
+        assert_close(s.std_dev, std_dev);
+        assert_close(s.ci95_high, 0.5 + 1.960 * std_dev / 40.0f64.sqrt());
+    }
+
+    #[test]
+    fn speedup_is_the_ratio_of_the_medians() {
+        let candidate = Summary {
+            median: 20.0,
+            ci95_low: 18.0,
+            ci95_high: 22.0,
+            ..Summary::default()
+        };
+        let baseline = Summary {
+            median: 10.0,
+            ci95_low: 9.0,
+            ci95_high: 11.0,
+            ..Summary::default()
+        };
+        let speedup = candidate.speedup_over(&baseline);
+        assert_close(speedup.ratio, 2.0);
+        assert_close(speedup.low, 18.0 / 11.0);
+        assert_close(speedup.high, 22.0 / 9.0);
+
+        // a median outside the intervals widens the range
+        let skewed = Summary {
+            median: 30.0,
+            ..candidate
+        };
+        let speedup = skewed.speedup_over(&baseline);
+        assert_close(speedup.ratio, 3.0);
+        assert_close(speedup.high, 3.0);
+    }
+
+    #[test]
+    fn speedup_over_a_zero_baseline() {
+        let candidate = Summary::from_samples(&[10.0, 12.0]);
+        let speedup = candidate.speedup_over(&Summary::default());
+        assert_close(speedup.ratio, 0.0);
+        assert_close(speedup.low, 0.0);
+        assert_close(speedup.high, 0.0);
+
+        // an interval reaching zero leaves the speedup unbounded above
+        let baseline = Summary {
+            median: 5.0,
+            ci95_low: 0.0,
+            ci95_high: 10.0,
+            ..Summary::default()
+        };
+        let speedup = candidate.speedup_over(&baseline);
+        assert_close(speedup.ratio, 11.0 / 5.0);
+        assert_eq!(speedup.high, f64::INFINITY);
+    }
+
+    #[test]
+    fn differs_significantly_with_welch_degrees_of_freedom() {
+        // equal variances: 18 degrees of freedom, t critical 2.101
+        assert!(differs_significantly(
+            &summary(10, 10.0, 1.0),
+            &summary(10, 11.0, 1.0)
+        ));
+        assert!(!differs_significantly(
+            &summary(10, 10.0, 1.0),
+            &summary(10, 10.8, 1.0)
+        ));
+        // t = 7 / sqrt(10.1) = 2.20 passes at 18 degrees of freedom, but
+        // unequal variances leave 9, where the critical value is 2.262
+        assert!(!differs_significantly(
+            &summary(10, 10.0, 1.0),
+            &summary(10, 17.0, 10.0)
+        ));
+        assert!(differs_significantly(
+            &summary(10, 10.0, 1.0),
+            &summary(10, 18.0, 10.0)
+        ));
+    }
+
+    #[test]
+    fn differs_significantly_without_variance_or_samples() {
+        assert!(!differs_significantly(
+            &summary(5, 10.0, 0.0),
+            &summary(5, 10.0, 0.0)
+        ));
+        assert!(differs_significantly(
+            &summary(5, 10.0, 0.0),
+            &summary(5, 10.5, 0.0)
+        ));
+        assert!(!differs_significantly(
+            &summary(1, 10.0, 0.0),
+            &summary(5, 20.0, 1.0)
+        ));
+    }
+
+    #[test]
+    fn fit_exponent_of_an_exact_power_law() {
+        let quadratic: Vec<(u64, f64)> = [10u64, 100, 1000]
+            .iter()
+            .map(|&n| (n, 1e9 / (n as f64).powi(2)))
+            .collect();
+        let fit = fit_exponent(&quadratic).unwrap();
+        assert_close(fit.exponent, 2.0);
+        assert_close(fit.r_squared, 1.0);
+
+        let constant = [(10, 500.0), (20, 500.0), (40, 500.0)];
+        let fit = fit_exponent(&constant).unwrap();
+        assert_close(fit.exponent, 0.0);
+        assert_close(fit.r_squared, 1.0);
+    }
+
+    #[test]
+    fn fit_exponent_needs_two_distinct_sizes() {
+        assert!(fit_exponent(&[]).is_none());
+        assert!(fit_exponent(&[(10, 1.0)]).is_none());
+        assert!(fit_exponent(&[(10, 1.0), (10, 2.0)]).is_none());
+        // points without a size or a rate are left out
+        assert!(fit_exponent(&[(0, 1.0), (10, 2.0), (20, 0.0)]).is_none());
+    }
+}
@@ -0,0 +1,238 @@
+use chrono::NaiveDate;
+use prettytable::{Cell, Row, Table};
+
+use crate::cli::{Format, SweepArgs, Timing};
+use crate::common::{self, AICodeGenStatus, Candidate, CandidateInfo};
+use crate::isolate::{self, Outcome};
+use crate::stats::{self, Fit, Speedup, Summary};
+use crate::task::Task;
+use crate::verify;
+
+// A single input size cannot tell an O(n²) candidate from an O(n log n) one
+// that happens to be as fast there. A sweep times every candidate, and the
+// baseline, at sizes growing geometrically over the task's sweep range, fits
+// time = c · n^k to the timings in log-log space and reports k next to the
+// speedup at the largest size both finished. Each timing runs in a child
+// process as in `run`; once a candidate times out, the larger sizes are skipped.
+
+pub struct Sweep {
+    pub task_name: String,
+    pub sizes: Vec<u64>,
+    /// The baseline first.
+    pub rows: Vec<SweepRow>,
+}
+
+pub struct SweepRow {
+    pub engine_name: String,
+    pub date: NaiveDate,
+    pub status: AICodeGenStatus,
+    /// One per size: the timing, or why there is none.
+    pub timings: Vec<Result<Summary, String>>,
+    pub fit: Option<Fit>,
+    /// At the largest size timed for both the candidate and the baseline.
+    pub speedup: Option<(u64, Speedup)>,
+    pub is_baseline: bool,
+}
+
+/// `steps` sizes from `from` to `to`, evenly spaced on a log scale.
+pub fn sizes(from: u64, to: u64, steps: u32) -> Vec<u64> {
+    let (from, to) = (from.max(1), to.max(from.max(1)));
+    if steps < 2 || from == to {
+        return vec![to];
+    }
+    let ratio = (to as f64 / from as f64).powf(1.0 / (steps - 1) as f64);
+    let mut sizes: Vec<u64> = (0..steps)
+        .map(|i| (from as f64 * ratio.powi(i as i32)).round() as u64)
+        .collect();
+    sizes.dedup();
+    sizes
+}
+
+/// Sweeps the selected tasks of the family over their sizes.
+pub fn sweep_family<T: Task>(
+    args: &SweepArgs,
+    family: &str,
+    tasks: &[T],
+    modules: &[CandidateInfo<T>],
+) -> Result<Vec<Sweep>, String> {
+    let baseline = &modules[0];
+    let mut sweeps = Vec::new();
+
+    for task in tasks
+        .iter()
+        .filter(|task| args.filter.task_selected(task.name()))
+    {
+        let (from, to) = task.sweep_range();
+        let sizes = sizes(args.from.unwrap_or(from), args.to.unwrap_or(to), args.steps);
+        let k = baseline
+            .position(task.name())
+            .ok_or_else(|| format!("task '{}' has no baseline", task.name()))?;
+
+        let mut rows = vec![time_row(
+            args,
+            family,
+            (0, k),
+            &baseline.engine_name,
+            &baseline.candidates[k],
+            &sizes,
+        )];
+        rows[0].is_baseline = true;
+
+        for (i, module) in modules.iter().enumerate().skip(1) {
+            if !args.filter.engine_selected(&module.engine_name) {
+                continue;
+            }
+            for (j, candidate) in module.candidates.iter().enumerate() {
+                if candidate.task == task.name() && candidate.status.has_code() {
+                    rows.push(time_row(
+                        args,
+                        family,
+                        (i, j),
+                        &module.engine_name,
+                        candidate,
+                        &sizes,
+                    ));
+                }
+            }
+        }
+
+        let (baseline_row, candidate_rows) = rows.split_first_mut().unwrap();
+        for row in candidate_rows {
+            row.speedup = sizes.iter().enumerate().rev().find_map(|(s, size)| {
+                match (&row.timings[s], &baseline_row.timings[s]) {
+                    (Ok(timing), Ok(baseline)) => Some((*size, timing.speedup_over(baseline))),
+                    _ => None,
+                }
+            });
+        }
+
+        sweeps.push(Sweep {
+            task_name: task.name().to_string(),
+            sizes,
+            rows,
+        });
+    }
+
+    Ok(sweeps)
+}
+
+/// Times candidate `function` of module `module` at every size.
+fn time_row<T: Task>(
+    args: &SweepArgs,
+    family: &str,
+    (module, function): (usize, usize),
+    engine_name: &str,
+    candidate: &Candidate<T>,
+    sizes: &[u64],
+) -> SweepRow {
+    let mut status = candidate.status;
+    let mut timings = Vec::new();
+    let mut stopped = false;
+
+    for size in sizes {
+        if stopped {
+            timings.push(Err("skipped".to_string()));
+            continue;
+        }
+        let options = Timing {
+            duration: args.duration,
+            input_size: Some(*size),
+            ..Timing::default()
+        };
+        eprintln!(
+            "timing {} / {} at size {}",
+            engine_name, candidate.function_name, size
+        );
+        let timing = match isolate::run_candidate(family, module, function, &options) {
+            Outcome::Finished {
+                matches,
+                caches,
+                summary,
+                ..
+            } => {
+                if let Some(matches) = matches {
+                    status = verify::observed_status(candidate.status, matches, caches);
+                }
+                Ok(summary)
+            }
+            Outcome::Timeout => Err(AICodeGenStatus::Timeout.to_string()),
+            Outcome::Panicked(message) => {
+                eprintln!("warning: {} / {}: {}", engine_name, candidate.task, message);
+                Err(AICodeGenStatus::Panicked.to_string())
+            }
+            Outcome::Crashed(reason) => {
+                eprintln!("warning: {} / {}: {}", engine_name, candidate.task, reason);
+                Err(AICodeGenStatus::Crashed.to_string())
+            }
+            Outcome::Failed(reason) => {
+                eprintln!("warning: {} / {}: {}", engine_name, candidate.task, reason);
+                Err(AICodeGenStatus::Failed.to_string())
+            }
+        };
+        stopped = timing.is_err();
+        timings.push(timing);
+    }
+
+    let points: Vec<(u64, f64)> = sizes
+        .iter()
+        .zip(&timings)
+        .filter_map(|(size, timing)| timing.as_ref().ok().map(|t| (*size, t.median)))
+        .collect();
+    SweepRow {
+        engine_name: engine_name.to_string(),
+        date: candidate.date,
+        status,
+        timings,
+        fit: stats::fit_exponent(&points),
+        speedup: None,
+        is_baseline: false,
+    }
+}
+
+/// Prints one table per task: iterations/second at each size, the fitted
+/// exponent and the speedup.
+pub fn print_sweeps(sweeps: &[Sweep], output_format: Format) {
+    for sweep in sweeps {
+        println!("\n\nScaling of function: {}", sweep.task_name);
+
+        let mut table = Table::new();
+        table.set_format(common::table_format(output_format));
+        let mut titles = vec![
+            Cell::new("AI Engine"),
+            Cell::new("Date"),
+            Cell::new("Status"),
+        ];
+        titles.extend(
+            sweep
+                .sizes
+                .iter()
+                .map(|size| Cell::new(&format!("n={}", size))),
+        );
+        titles.push(Cell::new("Complexity"));
+        titles.push(Cell::new("Speedup"));
+        table.set_titles(Row::new(titles));
+
+        for row in &sweep.rows {
+            let mut cells = vec![
+                Cell::new(&row.engine_name),
+                Cell::new(&row.date.to_string()),
+                Cell::new(&format!("{:?}", row.status)),
+            ];
+            cells.extend(row.timings.iter().map(|timing| match timing {
+                Ok(summary) => Cell::new(&format!("{:.2}", summary.median)),
+                Err(reason) => Cell::new(reason),
+            }));
+            cells.push(Cell::new(
+                &row.fit.map_or("-".to_string(), |fit| fit.to_string()),
+            ));
+            cells.push(Cell::new(&match (row.is_baseline, row.speedup) {
+                (true, _) => "-----".to_string(),
+                (false, Some((size, speedup))) => format!("{} at n={}", speedup, size),
+                (false, None) => "none".to_string(),
+            }));
+            table.add_row(Row::new(cells));
+        }
+
+        table.printstd();
+    }
+}
@@ -0,0 +1,485 @@
+use core::fmt;
+
+use crate::fuzz::Rng;
+
+// A task is one benchmark problem: the baseline and every AI attempt at it
+// share a signature, the inputs they are checked on and the input they are
+// timed on. Tasks with the same signature share a Task type, so adding a new
+// signature means implementing Task once rather than copying the harness.
+
+pub trait Task {
+    type Input: PartialEq;
+    type Output: PartialEq + fmt::Debug;
+    type Func: Copy;
+
+    /// Parameter and return types of `Func`, as written in Rust source.
+    const SIGNATURE: &'static str;
+
+    /// Name used by the candidates to say which task a function belongs to.
+    fn name(&self) -> &str;
+
+    /// Name of the baseline function in module_baseline.
+    fn function_name(&self) -> &str;
+
+    /// Inputs every candidate is checked on against the baseline.
+    fn verification_inputs(&self) -> Vec<Self::Input>;
+
+    /// Input the candidates are timed on.
+    fn benchmark_input(&self) -> Self::Input;
+
+    /// Input of roughly `size` units (characters, the value of n, ...) for
+    /// timing the candidates on something other than the benchmark input.
+    fn sized_input(&self, size: u64) -> Self::Input;
+
+    /// Smallest and largest size `sweep` times the candidates at, the largest
+    /// being about what the baseline can still be timed on.
+    fn sweep_range(&self) -> (u64, u64);
+
+    /// Input costing about as much as `input` but, most of the time, not
+    /// equal to it, for the timing pool and the caching check.
+    fn input_variant(&self, input: &Self::Input, rng: &mut Rng) -> Self::Input;
+
+    fn call(f: Self::Func, input: &Self::Input) -> Self::Output;
+
+    /// Rust expression calling `function` on `input`, for checks compiled
+    /// outside the harness.
+    fn call_source(function: &str, input: &Self::Input) -> String;
+
+    /// Random input for the fuzzer, small enough for the baseline to be quick on.
+    fn random_input(&self, rng: &mut Rng) -> Self::Input;
+
+    /// Inputs simpler than `input`, the most promising first, for shrinking a
+    /// counterexample found by the fuzzer.
+    fn shrink(&self, input: &Self::Input) -> Vec<Self::Input>;
+
+    fn outputs_match(&self, a: &Self::Output, b: &Self::Output) -> bool {
+        a == b
+    }
+
+    fn format_output(&self, output: &Self::Output) -> String {
+        format!("{:?}", output)
+    }
+}
+
+/// Finds the task a candidate function claims to belong to.
+pub fn find<'a, T: Task>(tasks: &'a [T], function_name: &str) -> Option<&'a T> {
+    tasks.iter().find(|task| task.name() == function_name)
+}
+
+/// Seed of the input pool, the same for every child so that the baseline and
+/// the candidates are timed on the same inputs.
+const POOL_SEED: u64 = 0x5EED_5EED_5EED_5EED;
+
+/// `input` followed by `size - 1` variants of it, for timing a candidate on
+/// inputs it cannot have precomputed the answers for.
+pub fn input_pool<T: Task>(task: &T, input: T::Input, size: usize) -> Vec<T::Input> {
+    let mut rng = Rng::new(POOL_SEED);
+    let variants: Vec<T::Input> = (1..size)
+        .map(|_| task.input_variant(&input, &mut rng))
+        .collect();
+    std::iter::once(input).chain(variants).collect()
+}
+
+/// Tasks of the form `fn(&str, &str) -> usize`.
+pub struct StringDistance {
+    name: &'static str,
+    function_name: &'static str,
+    inputs: Vec<(&'static str, &'static str)>,
+    benchmark: (&'static str, &'static str),
+    sweep: (u64, u64),
+    /// Character sets the fuzzer draws strings from.
+    alphabets: &'static [&'static str],
+}
+
+impl Task for StringDistance {
+    type Input = (String, String);
+    type Output = usize;
+    type Func = fn(&str, &str) -> usize;
+
+    const SIGNATURE: &'static str = "(&str, &str) -> usize";
+
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn function_name(&self) -> &str {
+        self.function_name
+    }
+
+    fn verification_inputs(&self) -> Vec<Self::Input> {
+        self.inputs
+            .iter()
+            .map(|(s, t)| (s.to_string(), t.to_string()))
+            .collect()
+    }
+
+    fn benchmark_input(&self) -> Self::Input {
+        (self.benchmark.0.to_string(), self.benchmark.1.to_string())
+    }
+
+    /// Both strings are `size` characters long, cut from the benchmark text
+    /// repeated as often as needed.
+    fn sized_input(&self, size: u64) -> Self::Input {
+        let cut = |text: &str| text.chars().cycle().take(size as usize).collect();
+        (cut(self.benchmark.0), cut(self.benchmark.1))
+    }
+
+    fn sweep_range(&self) -> (u64, u64) {
+        self.sweep
+    }
+
+    /// The same lengths, with about one character in eight replaced by another
+    /// of the same string.
+    fn input_variant(&self, input: &Self::Input, rng: &mut Rng) -> Self::Input {
+        (vary_string(&input.0, rng), vary_string(&input.1, rng))
+    }
+
+    fn call(f: Self::Func, input: &Self::Input) -> usize {
+        f(&input.0, &input.1)
+    }
+
+    fn call_source(function: &str, input: &Self::Input) -> String {
+        format!("{}({:?}, {:?})", function, input.0, input.1)
+    }
+
+    /// Two strings drawn from the same alphabet, the second often an edit of
+    /// the first, so that both near and far pairs come up.
+    fn random_input(&self, rng: &mut Rng) -> Self::Input {
+        let alphabet: Vec<char> = rng.pick(self.alphabets).chars().collect();
+        let s = random_string(rng, &alphabet);
+        let t = if rng.chance(1, 2) {
+            let mut t: Vec<char> = s.chars().collect();
+            for _ in 0..=rng.below(3) {
+                let at = rng.below(t.len() as u64 + 1) as usize;
+                match rng.below(3) {
+                    0 => t.insert(at, *rng.pick(&alphabet)),
+                    1 if at < t.len() => {
+                        t.remove(at);
+                    }
+                    _ if at < t.len() => t[at] = *rng.pick(&alphabet),
+                    _ => {}
+                }
+            }
+            t.into_iter().collect()
+        } else {
+            random_string(rng, &alphabet)
+        };
+        (s, t)
+    }
+
+    fn shrink(&self, input: &Self::Input) -> Vec<Self::Input> {
+        let (s, t) = input;
+        let mut simpler: Vec<Self::Input> = shrink_string(s)
+            .into_iter()
+            .map(|s| (s, t.clone()))
+            .collect();
+        simpler.extend(shrink_string(t).into_iter().map(|t| (s.clone(), t)));
+        simpler
+    }
+}
+
+const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
+const PRINTABLE_ASCII: &str = " !\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstuvwxyz{|}~";
+
+/// Alphabets for the tasks defined on ASCII text: few letters for many equal
+/// characters, and all of printable ASCII.
+const ASCII_ALPHABETS: &[&str] = &["ab", LOWERCASE, PRINTABLE_ASCII];
+
+/// The ASCII alphabets and text that is not ASCII (accents, CJK, emoji, a
+/// combining mark).
+const UNICODE_ALPHABETS: &[&str] = &[
+    "ab",
+    LOWERCASE,
+    PRINTABLE_ASCII,
+    "aeéèñüßøæ€",
+    "ab中文字🦀é\u{301}",
+];
+
+const FUZZ_MAX_LEN: u64 = 24;
+
+fn random_string(rng: &mut Rng, alphabet: &[char]) -> String {
+    let len = if rng.chance(1, 4) {
+        rng.below(4)
+    } else {
+        rng.below(FUZZ_MAX_LEN + 1)
+    };
+    (0..len).map(|_| *rng.pick(alphabet)).collect()
+}
+
+/// `s` with about one character in eight replaced by another of its
+/// characters, so the length and the alphabet stay the same.
+fn vary_string(s: &str, rng: &mut Rng) -> String {
+    let chars: Vec<char> = s.chars().collect();
+    chars
+        .iter()
+        .map(|&c| {
+            if rng.chance(1, 8) {
+                *rng.pick(&chars)
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+/// Shorter strings first (the halves, then one character less), then the
+/// same string with a character replaced by 'a'.
+fn shrink_string(s: &str) -> Vec<String> {
+    let chars: Vec<char> = s.chars().collect();
+    let mut simpler = Vec::new();
+    let mut chunk = chars.len() / 2;
+    while chunk > 0 {
+        for start in (0..chars.len()).step_by(chunk) {
+            let end = (start + chunk).min(chars.len());
+            simpler.push(chars[..start].iter().chain(&chars[end..]).collect());
+        }
+        chunk /= 2;
+    }
+    for (i, c) in chars.iter().enumerate() {
+        if *c != 'a' {
+            let mut replaced = chars.clone();
+            replaced[i] = 'a';
+            simpler.push(replaced.into_iter().collect());
+        }
+    }
+    simpler
+}
+
+/// Tasks of the form `fn(u64) -> u64`.
+pub struct U64Function {
+    name: &'static str,
+    function_name: &'static str,
+    inputs: Vec<u64>,
+    benchmark: u64,
+    /// Largest n the fuzzer tries; the baselines are naive.
+    fuzz_max: u64,
+    sweep: (u64, u64),
+}
+
+impl Task for U64Function {
+    type Input = u64;
+    type Output = u64;
+    type Func = fn(u64) -> u64;
+
+    const SIGNATURE: &'static str = "(u64) -> u64";
+
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn function_name(&self) -> &str {
+        self.function_name
+    }
+
+    fn verification_inputs(&self) -> Vec<u64> {
+        self.inputs.clone()
+    }
+
+    fn benchmark_input(&self) -> u64 {
+        self.benchmark
+    }
+
+    fn sized_input(&self, size: u64) -> u64 {
+        size
+    }
+
+    fn sweep_range(&self) -> (u64, u64) {
+        self.sweep
+    }
+
+    /// A value within n/32 of n, as the baselines' cost grows with n.
+    fn input_variant(&self, input: &u64, rng: &mut Rng) -> u64 {
+        let spread = (input / 32).max(1);
+        input.saturating_sub(spread) + rng.below(2 * spread + 1)
+    }
+
+    fn call(f: Self::Func, input: &u64) -> u64 {
+        f(*input)
+    }
+
+    fn call_source(function: &str, input: &u64) -> String {
+        format!("{}({})", function, input)
+    }
+
+    /// Small n, where the special cases are, squares and their neighbours,
+    /// where square root loops go wrong, or anything up to `fuzz_max`.
+    fn random_input(&self, rng: &mut Rng) -> u64 {
+        match rng.below(4) {
+            0 => rng.below(17.min(self.fuzz_max + 1)),
+            1 => {
+                let root = rng.below(self.fuzz_max.isqrt() + 1);
+                (root * root + rng.below(3))
+                    .saturating_sub(1)
+                    .min(self.fuzz_max)
+            }
+            _ => rng.below(self.fuzz_max + 1),
+        }
+    }
+
+    /// 0, then values closer and closer to n.
+    fn shrink(&self, input: &u64) -> Vec<u64> {
+        let n = *input;
+        let mut simpler = Vec::new();
+        if n > 0 {
+            simpler.push(0);
+        }
+        let mut step = n / 2;
+        while step > 0 {
+            if n - step > 0 {
+                simpler.push(n - step);
+            }
+            step /= 2;
+        }
+        if n > 1 {
+            simpler.push(n - 1);
+        }
+        simpler.dedup();
+        simpler
+    }
+}
+
+const HAMLET_1: &str = "To be, or not to be, that is the question:
+                        Whether 'tis nobler in the mind to suffer
+                        The slings and arrows of outrageous fortune,
+                        Or to take arms against a sea of troubles,
+                        And by opposing end them: to die, to sleep
+                        No more; and by a sleep, to say we end
+                        The heart-ache, and the thousand natural shocks";
+
+const HAMLET_2: &str = "That Flesh is heir to? 'Tis a consummation:
+                        Devoutly to be wished. To die, to sleep,
+                        To sleep, perchance to Dream; aye, there's the rub;
+                        For in that sleep of death, what dreams may come,
+                        When we have shuffled off this mortal coil,
+                        Must give us pause.
+                         There's the respect
+                        That makes Calamity of so long life:
+                        For who would bear the Whips and Scorns of time,";
+
+// The opening of the same speech in Schlegel's and in François-Victor
+// Hugo's translations, for timing the tasks defined on Unicode text.
+const HAMLET_DE: &str = "Sein oder Nichtsein, das ist hier die Frage:
+                         Ob’s edler im Gemüt, die Pfeil’ und Schleudern
+                         Des wütenden Geschicks erdulden oder,
+                         Sich waffnend gegen eine See von Plagen,
+                         Durch Widerstand sie enden? Sterben – schlafen –
+                         Nichts weiter! – und zu wissen, daß ein Schlaf
+                         Das Herzweh und die tausend Stöße endet";
+
+const HAMLET_FR: &str = "Être, ou ne pas être, c’est là la question.
+                         Y a-t-il plus de noblesse d’âme à subir
+                         la fronde et les flèches de la fortune outrageante,
+                         ou bien à s’armer contre une mer de douleurs
+                         et à l’arrêter par une révolte ? Mourir… dormir,
+                         rien de plus ;… et dire que par ce sommeil
+                         nous mettons fin aux maux du cœur";
+
+/// Pairs on which counting bytes, chars or graphemes gives different answers.
+const UNICODE_INPUTS: &[(&str, &str)] = &[
+    ("", "é"),
+    ("café", "cafe"),
+    ("naïve", "naive"),
+    ("文字", "文"),
+    ("🦀rust", "rust🦀"),
+    ("e\u{301}", "é"),
+    ("cafe\u{301}", "café"),
+    ("👩‍👩‍👧", "👩‍👩‍👦"),
+    ("🇫🇷🇩🇪", "🇩🇪🇫🇷"),
+    ("Straße", "Strasse"),
+];
+
+pub fn string_distance_tasks() -> Vec<StringDistance> {
+    let ascii_inputs = vec![
+        ("", ""),
+        ("", "abc"),
+        ("abc", ""),
+        ("kitten", "sitting"),
+        ("flaw", "lawn"),
+        ("intention", "execution"),
+        ("To be, or not to be", "To be, or not to be"),
+        (
+            "Whether 'tis nobler in the mind to suffer",
+            "The slings and arrows of outrageous fortune,",
+        ),
+    ];
+    let unicode_inputs: Vec<(&str, &str)> =
+        ascii_inputs.iter().chain(UNICODE_INPUTS).copied().collect();
+
+    vec![
+        StringDistance {
+            name: "levenshstein distance",
+            function_name: "levenshtein_distance",
+            inputs: ascii_inputs,
+            benchmark: (HAMLET_1, HAMLET_2),
+            // the baseline walks the strings for every cell, O(n³)
+            sweep: (8, 256),
+            // the baseline counts bytes, so the task is only defined on ASCII
+            alphabets: ASCII_ALPHABETS,
+        },
+        StringDistance {
+            name: "levenshtein distance (chars)",
+            function_name: "levenshtein_distance_chars",
+            inputs: unicode_inputs.clone(),
+            benchmark: (HAMLET_DE, HAMLET_FR),
+            sweep: (16, 2048),
+            alphabets: UNICODE_ALPHABETS,
+        },
+        StringDistance {
+            name: "levenshtein distance (graphemes)",
+            function_name: "levenshtein_distance_graphemes",
+            inputs: unicode_inputs,
+            benchmark: (HAMLET_DE, HAMLET_FR),
+            sweep: (16, 2048),
+            alphabets: UNICODE_ALPHABETS,
+        },
+    ]
+}
+
+pub fn u64_tasks() -> Vec<U64Function> {
+    // kept small enough that the naive baseline finishes quickly
+    let divisor_inputs = vec![1, 2, 3, 12, 97, 360, 1000];
+
+    vec![
+        U64Function {
+            name: "sum of divisors",
+            function_name: "sum_of_divisors",
+            inputs: divisor_inputs.clone(),
+            benchmark: 1000,
+            fuzz_max: 100_000,
+            sweep: (1_000, 1_000_000),
+        },
+        U64Function {
+            name: "count of primes",
+            function_name: "count_primes",
+            inputs: divisor_inputs.clone(),
+            benchmark: 1000,
+            fuzz_max: 20_000,
+            sweep: (1_000, 100_000),
+        },
+        U64Function {
+            name: "fibonacci",
+            function_name: "fibonacci",
+            inputs: vec![1, 2, 3, 10, 20, 30],
+            // the baseline is exponential, so it has to be timed on a small n
+            benchmark: 30,
+            fuzz_max: 25,
+            sweep: (4, 32),
+        },
+        U64Function {
+            name: "highly composite",
+            function_name: "highly_composite",
+            inputs: divisor_inputs.clone(),
+            benchmark: 1000,
+            fuzz_max: 2_000,
+            sweep: (100, 10_000),
+        },
+        U64Function {
+            name: "sum of proper divisors",
+            function_name: "sum_of_proper_divisors",
+            inputs: divisor_inputs,
+            benchmark: 1000,
+            fuzz_max: 100_000,
+            sweep: (1_000, 1_000_000),
+        },
+    ]
+}
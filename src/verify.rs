@@ -0,0 +1,123 @@
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use crate::common::AICodeGenStatus;
+use crate::common::CandidateInfo;
+use crate::fuzz::Rng;
+use crate::task::Task;
+
+// Every candidate is run on the same inputs as the baseline and the outputs are
+// compared. The status that ends up in the results table is the one observed
+// here, not the one typed into get_candidates()/get_candidates2().
+//
+// A candidate can also be correct but keep its results in a static or a
+// thread_local, so that every timed call after the first is a lookup. Such a
+// candidate answers an input it has seen much faster than a new one of the
+// same cost, which is what `caches_results` looks for.
+
+/// New inputs `caches_results` times the candidate on.
+const CACHE_PROBES: usize = 5;
+
+/// How many times faster than the first call on an input the repeated calls
+/// must be, for the median probe, to take the candidate as caching results.
+const CACHE_SPEEDUP: f64 = 10.0;
+
+const CACHE_SEED: u64 = 0xCAC4E;
+
+/// The declared status of a candidate next to the one derived from its output.
+#[derive(Copy, Clone, Debug)]
+pub struct Verdict {
+    pub declared: AICodeGenStatus,
+    pub observed: AICodeGenStatus,
+}
+
+impl Verdict {
+    pub fn disagrees(&self) -> bool {
+        self.declared != self.observed
+    }
+}
+
+/// Derives the status of a candidate from whether its output matched the
+/// baseline and whether it caches results. Candidates that never produced
+/// runnable code keep their declared status.
+pub fn observed_status(declared: AICodeGenStatus, matches: bool, caches: bool) -> AICodeGenStatus {
+    match declared {
+        _ if !declared.has_code() => declared,
+        _ if !matches => AICodeGenStatus::IncorrectResult,
+        _ if caches => AICodeGenStatus::CachesResults,
+        AICodeGenStatus::SecondTryOk => AICodeGenStatus::SecondTryOk,
+        _ => AICodeGenStatus::Ok,
+    }
+}
+
+/// Checks `function` against the baseline's function for `task` on every
+/// verification input. None if the baseline has no function for the task.
+pub fn matches_baseline<T: Task>(
+    task: &T,
+    baseline: &CandidateInfo<T>,
+    function: T::Func,
+) -> Option<bool> {
+    let reference = baseline.candidates[baseline.position(task.name())?].function;
+
+    Some(
+        task.verification_inputs()
+            .iter()
+            .all(|input| task.outputs_match(&T::call(function, input), &T::call(reference, input))),
+    )
+}
+
+/// Whether `function` answers inputs it was called on before much faster than
+/// new ones. Each probe is a variant of `input`, never seen by the candidate,
+/// timed on its first call and then on the fastest of three repeated calls.
+/// No probe starts once `budget` is used up, so a slow candidate gets fewer.
+pub fn caches_results<T: Task>(
+    task: &T,
+    function: T::Func,
+    input: &T::Input,
+    budget: Duration,
+) -> bool {
+    let start = Instant::now();
+    let time_call = |input: &T::Input| {
+        let start = Instant::now();
+        black_box(T::call(black_box(function), black_box(input)));
+        start.elapsed().as_secs_f64()
+    };
+
+    let mut rng = Rng::new(CACHE_SEED);
+    let mut seen = vec![];
+    let mut ratios = Vec::new();
+    time_call(input);
+    for _ in 0..CACHE_PROBES * 4 {
+        if start.elapsed() >= budget {
+            break;
+        }
+        let probe = task.input_variant(input, &mut rng);
+        if probe == *input || seen.contains(&probe) {
+            continue;
+        }
+        let first = time_call(&probe);
+        let repeated = (0..3)
+            .map(|_| time_call(&probe))
+            .fold(f64::INFINITY, f64::min);
+        ratios.push(first / repeated.max(f64::MIN_POSITIVE));
+        seen.push(probe);
+        if ratios.len() == CACHE_PROBES {
+            break;
+        }
+    }
+
+    ratios.sort_by(f64::total_cmp);
+    ratios
+        .get(ratios.len() / 2)
+        .is_some_and(|ratio| *ratio > CACHE_SPEEDUP)
+}
+
+/// Prints a warning if the declared status of a candidate is not what was observed.
+pub fn report_disagreement(engine_name: &str, function_name: &str, verdict: &Verdict) {
+    if verdict.disagrees() {
+        eprintln!(
+            "warning: {} / {}: declared {} but observed {}",
+            engine_name, function_name, verdict.declared, verdict.observed
+        );
+    }
+}